@@ -15,6 +15,27 @@ pub enum ReviewError {
     // Error 3
     #[error("Rating greater than 5 or less than 1")]
     InvalidRating,
+    // Error 4
+    #[error("Signer is not the reviewer")]
+    InvalidReviewer,
+    // Error 5
+    #[error("Signer is not the commenter")]
+    InvalidCommenter,
+    // Error 6
+    #[error("Comment has been deleted")]
+    CommentDeleted,
+    // Error 7
+    #[error("Token account is not the author's reward token account")]
+    InvalidTokenAccount,
+    // Error 8
+    #[error("Signer is not the moderator")]
+    InvalidModerator,
+    // Error 9
+    #[error("Tip destination is not the reviewer")]
+    InvalidTipDestination,
+    // Error 10
+    #[error("Signer is not the program admin")]
+    InvalidAdmin,
 }
 
 impl From<ReviewError> for ProgramError {
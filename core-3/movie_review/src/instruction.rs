@@ -1,5 +1,5 @@
 use borsh::BorshDeserialize;
-use solana_program::program_error::ProgramError;
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
 
 pub enum MovieInstruction {
     AddMovieReview {
@@ -15,6 +15,44 @@ pub enum MovieInstruction {
     AddComment {
         comment: String,
     },
+    DeleteMovieReview,
+    VoteOnReview {
+        upvote: bool,
+    },
+    UpdateComment {
+        comment: String,
+    },
+    DeleteComment,
+    ReplyToComment {
+        comment: String,
+    },
+    InitializeTokenMint {
+        review_reward: u64,
+        comment_reward: u64,
+    },
+    SetModerator {
+        moderator: Pubkey,
+    },
+    HideReview,
+    UnhideReview,
+    HideComment,
+    TipReviewer {
+        amount: u64,
+        currency: TipCurrency,
+    },
+    MigrateReview,
+    UpdateTokenConfig {
+        admin: Pubkey,
+        review_reward: u64,
+        comment_reward: u64,
+    },
+}
+
+// SPL tips are paid in the program's reward token so tip totals stay comparable
+#[derive(BorshDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum TipCurrency {
+    Sol,
+    RewardToken,
 }
 
 impl MovieInstruction {
@@ -47,6 +85,58 @@ impl MovieInstruction {
                     comment: payload.comment,
                 }
             }
+            3 => Self::DeleteMovieReview,
+            4 => {
+                let payload = VotePayload::try_from_slice(rest).unwrap();
+                Self::VoteOnReview {
+                    upvote: payload.upvote,
+                }
+            }
+            5 => {
+                let payload = CommentPayload::try_from_slice(rest).unwrap();
+                Self::UpdateComment {
+                    comment: payload.comment,
+                }
+            }
+            6 => Self::DeleteComment,
+            7 => {
+                let payload = CommentPayload::try_from_slice(rest).unwrap();
+                Self::ReplyToComment {
+                    comment: payload.comment,
+                }
+            }
+            8 => {
+                let payload = TokenMintPayload::try_from_slice(rest).unwrap();
+                Self::InitializeTokenMint {
+                    review_reward: payload.review_reward,
+                    comment_reward: payload.comment_reward,
+                }
+            }
+            9 => {
+                let payload = ModeratorPayload::try_from_slice(rest).unwrap();
+                Self::SetModerator {
+                    moderator: payload.moderator,
+                }
+            }
+            10 => Self::HideReview,
+            11 => Self::UnhideReview,
+            12 => Self::HideComment,
+            13 => {
+                let payload = TipPayload::try_from_slice(rest).unwrap();
+                Self::TipReviewer {
+                    amount: payload.amount,
+                    currency: payload.currency,
+                }
+            }
+            14 => Self::MigrateReview,
+            15 => {
+                let payload = TokenConfigPayload::try_from_slice(rest).unwrap();
+                Self::UpdateTokenConfig {
+                    admin: payload.admin,
+                    review_reward: payload.review_reward,
+                    comment_reward: payload.comment_reward,
+                }
+            }
             _ => return Err(ProgramError::InvalidInstructionData),
         })
     }
@@ -63,3 +153,32 @@ struct MovieReviewPayload {
 struct CommentPayload {
     comment: String,
 }
+
+#[derive(BorshDeserialize)]
+struct VotePayload {
+    upvote: bool,
+}
+
+#[derive(BorshDeserialize)]
+struct TokenMintPayload {
+    review_reward: u64,
+    comment_reward: u64,
+}
+
+#[derive(BorshDeserialize)]
+struct TokenConfigPayload {
+    admin: Pubkey,
+    review_reward: u64,
+    comment_reward: u64,
+}
+
+#[derive(BorshDeserialize)]
+struct ModeratorPayload {
+    moderator: Pubkey,
+}
+
+#[derive(BorshDeserialize)]
+struct TipPayload {
+    amount: u64,
+    currency: TipCurrency,
+}
@@ -1,5 +1,6 @@
 pub mod entrypoint;
 pub mod error;
 pub mod instruction;
+pub mod pda;
 pub mod processor;
 pub mod state;
@@ -0,0 +1,266 @@
+use solana_program::{hash::hashv, pubkey::Pubkey};
+use std::ops::Range;
+
+// Seeds can be at most 32 bytes, which is why review addresses use a hash of the title
+pub const MAX_SEED_LEN: usize = 32;
+
+pub const MOVIE_SEED: &[u8] = b"movie";
+pub const TOKEN_MINT_SEED: &[u8] = b"token_mint";
+pub const MINT_AUTH_SEED: &[u8] = b"token_auth";
+pub const TOKEN_CONFIG_SEED: &[u8] = b"token_config";
+pub const MODERATOR_SEED: &[u8] = b"moderator";
+pub const REVIEW_REWARD_SEED: &[u8] = b"review_reward";
+pub const COMMENT_REWARD_SEED: &[u8] = b"comment_reward";
+
+// Titles are trimmed, whitespace-collapsed and lowercased before hashing so
+// "The Matrix" and "  the matrix " land on the same review address
+pub fn normalize_title(title: &str) -> String {
+    title
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+// SHA-256 of the normalized title, usable as a seed for titles of any length
+pub fn title_seed(title: &str) -> [u8; 32] {
+    hashv(&[normalize_title(title).as_bytes()]).to_bytes()
+}
+
+pub fn find_review_address(program_id: &Pubkey, reviewer: &Pubkey, title: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[reviewer.as_ref(), &title_seed(title)], program_id)
+}
+
+// Reviews created before title hashing were seeded with the raw title bytes.
+// Those accounts are still accepted until MigrateReview moves them
+pub fn find_legacy_review_address(
+    program_id: &Pubkey,
+    reviewer: &Pubkey,
+    title: &str,
+) -> Option<(Pubkey, u8)> {
+    if title.len() > MAX_SEED_LEN {
+        return None;
+    }
+    Some(Pubkey::find_program_address(
+        &[reviewer.as_ref(), title.as_bytes()],
+        program_id,
+    ))
+}
+
+pub fn is_legacy_review_address(
+    program_id: &Pubkey,
+    reviewer: &Pubkey,
+    title: &str,
+    review: &Pubkey,
+) -> bool {
+    matches!(
+        find_legacy_review_address(program_id, reviewer, title),
+        Some((legacy, _)) if legacy == *review
+    )
+}
+
+// True if `review` is either the hashed or the legacy address for this reviewer and title
+pub fn is_review_address(
+    program_id: &Pubkey,
+    reviewer: &Pubkey,
+    title: &str,
+    review: &Pubkey,
+) -> bool {
+    find_review_address(program_id, reviewer, title).0 == *review
+        || is_legacy_review_address(program_id, reviewer, title, review)
+}
+
+pub fn find_comment_counter_address(program_id: &Pubkey, review: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[review.as_ref(), "comment".as_ref()], program_id)
+}
+
+// One aggregate per movie, shared by every reviewer of that title
+pub fn find_movie_address(program_id: &Pubkey, title: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MOVIE_SEED, &title_seed(title)], program_id)
+}
+
+// Replies are numbered per comment, the same way comments are numbered per review
+pub fn find_reply_counter_address(program_id: &Pubkey, comment: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[comment.as_ref(), "reply".as_ref()], program_id)
+}
+
+pub fn find_review_score_address(program_id: &Pubkey, review: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[review.as_ref(), "score".as_ref()], program_id)
+}
+
+pub fn find_tip_stats_address(program_id: &Pubkey, review: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[review.as_ref(), "tips".as_ref()], program_id)
+}
+
+pub fn find_vote_receipt_address(
+    program_id: &Pubkey,
+    review: &Pubkey,
+    voter: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[review.as_ref(), voter.as_ref()], program_id)
+}
+
+// The reward mint, its authority and its config are singletons of the program
+pub fn find_token_mint_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TOKEN_MINT_SEED], program_id)
+}
+
+pub fn find_mint_auth_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MINT_AUTH_SEED], program_id)
+}
+
+pub fn find_token_config_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TOKEN_CONFIG_SEED], program_id)
+}
+
+pub fn find_moderator_config_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MODERATOR_SEED], program_id)
+}
+
+// Reward receipts are never closed, so deleting and re-adding a review or
+// comment doesn't pay out again. Reviews are keyed like their address,
+// comments once per commenter and review
+pub fn find_review_reward_address(
+    program_id: &Pubkey,
+    reviewer: &Pubkey,
+    title: &str,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[REVIEW_REWARD_SEED, reviewer.as_ref(), &title_seed(title)],
+        program_id,
+    )
+}
+
+pub fn find_comment_reward_address(
+    program_id: &Pubkey,
+    review: &Pubkey,
+    commenter: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[COMMENT_REWARD_SEED, review.as_ref(), commenter.as_ref()],
+        program_id,
+    )
+}
+
+// Numbered accounts under a parent, comments of a review or replies of a comment.
+// Entry `i` is seeded with the parent key and `i` as big endian bytes, the index
+// the counter handed out and the one stored in `MovieComment::count`
+pub struct Seq<'a> {
+    program_id: &'a Pubkey,
+    parent: &'a Pubkey,
+}
+
+impl<'a> Seq<'a> {
+    pub fn new(program_id: &'a Pubkey, parent: &'a Pubkey) -> Self {
+        Self { program_id, parent }
+    }
+
+    pub fn find_address(&self, index: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[self.parent.as_ref(), &index.to_be_bytes()],
+            self.program_id,
+        )
+    }
+
+    // Addresses for `indexes` oldest first, `.rev()` pages back from the newest
+    pub fn addresses(
+        &self,
+        indexes: Range<u64>,
+    ) -> impl DoubleEndedIterator<Item = (u64, Pubkey)> + '_ {
+        indexes.map(move |index| (index, self.find_address(index).0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn normalize_title_trims_collapses_and_lowercases() {
+        assert_eq!(normalize_title("The Matrix"), "the matrix");
+        assert_eq!(normalize_title("  the   MATRIX\t\n"), "the matrix");
+        assert_eq!(normalize_title(" Up\u{3000}"), "up");
+        assert_eq!(normalize_title("Amélie"), "amélie");
+        assert_eq!(normalize_title("ΟΔΥΣΣΕΥΣ"), "οδυσσευς");
+        assert_eq!(normalize_title(" \t "), "");
+    }
+
+    // The sets where JS `\s` and Rust's char::is_whitespace disagree
+    #[test]
+    fn normalize_title_uses_rust_whitespace() {
+        assert_eq!(normalize_title("A\u{0085}B"), "a b");
+        assert_eq!(normalize_title("\u{feff}Alien"), "\u{feff}alien");
+    }
+
+    // Hashes produced by normalizeTitle + sha256 in solana-movie-client, the
+    // client derives review and movie addresses from the same seed
+    #[test]
+    fn title_seed_matches_client() {
+        let vectors = [
+            (
+                "The Matrix",
+                "7e89dd9170bc7601176fbe0ee4ca7220947df1d07419a98d49250ca3f2369060",
+            ),
+            (
+                "  the   MATRIX\t\n",
+                "7e89dd9170bc7601176fbe0ee4ca7220947df1d07419a98d49250ca3f2369060",
+            ),
+            (
+                "Amélie",
+                "5f78ac01bde900c5e01be9f12cd565c62c6e6363796d30fa5748d00187357be8",
+            ),
+            (
+                "A\u{0085}B",
+                "c8687a08aa5d6ed2044328fa6a697ab8e96dc34291e8c2034ae8c38e6fcc6d65",
+            ),
+            (
+                "\u{feff}Alien",
+                "5acd532a49ca741aa51865f16addcdf11dcf9c4f997bc0544518550ffa290739",
+            ),
+            (
+                " Up\u{3000}",
+                "75a288c0d6898c5f7b054590845978a82a3ad79fcce3d43ff68a7501e5a91ee9",
+            ),
+            (
+                "ΟΔΥΣΣΕΥΣ",
+                "3c70e8e70fb6820b6ae6de04be9249923ba46e8aaa0f6ecfae7a1ee8880f574d",
+            ),
+        ];
+
+        for (title, expected) in vectors {
+            assert_eq!(hex(&title_seed(title)), expected, "title {:?}", title);
+        }
+    }
+
+    #[test]
+    fn legacy_address_only_for_short_titles() {
+        let program_id = Pubkey::new_unique();
+        let reviewer = Pubkey::new_unique();
+        let title = "The Matrix";
+
+        let (legacy, _) = find_legacy_review_address(&program_id, &reviewer, title).unwrap();
+        assert!(is_legacy_review_address(
+            &program_id,
+            &reviewer,
+            title,
+            &legacy
+        ));
+        assert!(is_review_address(&program_id, &reviewer, title, &legacy));
+
+        let (hashed, _) = find_review_address(&program_id, &reviewer, title);
+        assert_ne!(hashed, legacy);
+        assert!(!is_legacy_review_address(
+            &program_id,
+            &reviewer,
+            title,
+            &hashed
+        ));
+
+        let long_title = "a".repeat(MAX_SEED_LEN + 1);
+        assert!(find_legacy_review_address(&program_id, &reviewer, &long_title).is_none());
+    }
+}
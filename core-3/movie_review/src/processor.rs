@@ -1,19 +1,30 @@
 use crate::error::ReviewError;
-use crate::instruction::MovieInstruction;
-use crate::state::{MovieAccountState, MovieComment, MovieCommentCounter};
+use crate::instruction::{MovieInstruction, TipCurrency};
+use crate::pda;
+use crate::state::{
+    ModeratorConfig, MovieAccountState, MovieAggregate, MovieComment, MovieCommentCounter,
+    ReviewScore, RewardReceipt, TipStats, TokenConfig, VoteReceipt,
+};
 use borsh::BorshSerialize;
 use solana_program::program_pack::IsInitialized;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     borsh::try_from_slice_unchecked,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
     entrypoint::ProgramResult,
     msg,
-    program::invoke_signed,
+    program::{invoke, invoke_signed},
     program_error::ProgramError,
+    program_pack::Pack,
+    program_utils::limited_deserialize,
     pubkey::Pubkey,
-    system_instruction,
+    system_instruction, system_program,
     sysvar::{rent::Rent, Sysvar},
 };
+use spl_token::{
+    instruction::initialize_mint, instruction::mint_to, instruction::transfer, state::Account,
+    state::Mint,
+};
 use std::convert::TryInto;
 
 pub fn add_movie_review(
@@ -36,6 +47,8 @@ pub fn add_movie_review(
 
     // New account to store comment count
     let pda_counter = next_account_info(account_info_iter)?;
+    let movie_aggregate = next_account_info(account_info_iter)?;
+    let rewards = RewardAccounts::next(account_info_iter)?;
 
     // Signer check
     if !initializer.is_signer {
@@ -43,10 +56,9 @@ pub fn add_movie_review(
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    let (pda, bump_seed) = Pubkey::find_program_address(
-        &[initializer.key.as_ref(), title.as_bytes().as_ref()],
-        program_id,
-    );
+    // Hashing the title keeps the seed within 32 bytes however long the title is
+    let title_seed = pda::title_seed(&title);
+    let (pda, bump_seed) = pda::find_review_address(program_id, initializer.key, &title);
 
     // PDA check
     if pda != *pda_account.key {
@@ -54,34 +66,21 @@ pub fn add_movie_review(
         return Err(ProgramError::InvalidArgument);
     }
 
-    let account_len: usize = 1000;
+    // Only pay rent for what the review actually needs
+    let account_len = MovieAccountState::get_account_size(title.clone(), description.clone());
 
-    if MovieAccountState::get_account_size(title.clone(), description.clone()) > account_len {
+    if account_len > MovieAccountState::MAX_SIZE {
         msg!("Data length is larger than 1000 bytes");
         return Err(ReviewError::InvalidDataLength.into());
     }
 
-    let rent = Rent::get()?;
-    let rent_lamports = rent.minimum_balance(account_len);
-
-    invoke_signed(
-        &system_instruction::create_account(
-            initializer.key,
-            pda_account.key,
-            rent_lamports,
-            account_len.try_into().unwrap(),
-            program_id,
-        ),
-        &[
-            initializer.clone(),
-            pda_account.clone(),
-            system_program.clone(),
-        ],
-        &[&[
-            initializer.key.as_ref(),
-            title.as_bytes().as_ref(),
-            &[bump_seed],
-        ]],
+    allocate_pda_account(
+        program_id,
+        initializer,
+        pda_account,
+        system_program,
+        account_len,
+        &[initializer.key.as_ref(), &title_seed, &[bump_seed]],
     )?;
 
     msg!("PDA created: {}", pda);
@@ -97,71 +96,116 @@ pub fn add_movie_review(
         return Err(ReviewError::InvalidRating.into());
     }
 
+    let mut aggregate_data = load_or_create_movie_aggregate(
+        program_id,
+        initializer,
+        movie_aggregate,
+        system_program,
+        &title,
+    )?;
+    aggregate_data.add_rating(rating);
+    aggregate_data.serialize(&mut &mut movie_aggregate.data.borrow_mut()[..])?;
+
     account_data.discriminator = MovieAccountState::DISCRIMINATOR.to_string();
     account_data.reviewer = *initializer.key;
     account_data.title = title;
     account_data.rating = rating;
     account_data.description = description;
+    account_data.hidden = false;
+    account_data.legacy_review = None;
     account_data.is_initialized = true;
 
-    msg!("Creating comment counter");
-    let rent = Rent::get()?;
-    let counter_rent_lamports = rent.minimum_balance(MovieCommentCounter::SIZE);
+    let counter_data = load_or_create_comment_counter(
+        program_id,
+        initializer,
+        pda_account,
+        pda_counter,
+        system_program,
+    )?;
+    account_data.first_comment = counter_data.counter;
+
+    msg!("serializing account");
+    account_data.serialize(&mut &mut pda_account.data.borrow_mut()[..])?;
+    msg!("state account serialized");
+
+    let (_receipt, receipt_bump) =
+        pda::find_review_reward_address(program_id, initializer.key, &account_data.title);
+    let review_reward = load_reward_config(program_id, rewards.token_config)?
+        .map_or(0, |config| config.review_reward);
+    mint_reward(
+        program_id,
+        initializer,
+        system_program,
+        &rewards,
+        review_reward,
+        &[
+            pda::REVIEW_REWARD_SEED,
+            initializer.key.as_ref(),
+            &title_seed,
+            &[receipt_bump],
+        ],
+    )?;
+
+    Ok(())
+}
 
+// Deleting a review leaves its counter behind, a review added at the same
+// address carries on numbering from there so new comments don't land on the
+// old ones' addresses
+fn load_or_create_comment_counter<'a>(
+    program_id: &Pubkey,
+    initializer: &AccountInfo<'a>,
+    pda_account: &AccountInfo<'a>,
+    pda_counter: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+) -> Result<MovieCommentCounter, ProgramError> {
     // Deriving the address and validating that the correct seeds were passed in
-    let (counter, counter_bump) =
-        Pubkey::find_program_address(&[pda.as_ref(), "comment".as_ref()], program_id);
+    let (counter, counter_bump) = pda::find_comment_counter_address(program_id, pda_account.key);
     if counter != *pda_counter.key {
         msg!("Invalid seeds for PDA");
         return Err(ProgramError::InvalidArgument);
     }
 
-    // Creating the comment counter account
-    invoke_signed(
-        &system_instruction::create_account(
-            initializer.key,                               // Rent payer
-            pda_counter.key,       // Address who we're creating the account for
-            counter_rent_lamports, // Amount of rent to put into the account
-            MovieCommentCounter::SIZE.try_into().unwrap(), // Size of the account
-            program_id,
-        ),
-        &[
-            // List of accounts that will be read from/written to
-            initializer.clone(),
-            pda_counter.clone(),
-            system_program.clone(),
-        ],
+    if pda_counter.owner == program_id {
+        let counter_data =
+            try_from_slice_unchecked::<MovieCommentCounter>(&pda_counter.data.borrow())?;
+        if !counter_data.is_initialized() {
+            msg!("Comment counter is not initialized");
+            return Err(ReviewError::UninitializedAccount.into());
+        }
+        msg!("Reusing comment counter at {}", counter_data.counter);
+        return Ok(counter_data);
+    }
+
+    msg!("Creating comment counter");
+    allocate_pda_account(
+        program_id,
+        initializer,
+        pda_counter,
+        system_program,
+        MovieCommentCounter::SIZE,
         // Seeds for the PDA
         // PDA account
         // The string "comment"
-        &[&[pda.as_ref(), "comment".as_ref(), &[counter_bump]]],
+        &[
+            pda_account.key.as_ref(),
+            "comment".as_ref(),
+            &[counter_bump],
+        ],
     )?;
     msg!("Comment counter created");
 
-    // Deserialize the newly created counter account
-    let mut counter_data =
-        try_from_slice_unchecked::<MovieCommentCounter>(&pda_counter.data.borrow()).unwrap();
-
-    msg!("checking if counter account is already initialized");
-    if counter_data.is_initialized() {
-        msg!("Account already initialized");
-        return Err(ProgramError::AccountAlreadyInitialized);
-    }
-
-    counter_data.discriminator = MovieCommentCounter::DISCRIMINATOR.to_string();
-    counter_data.counter = 0;
-    counter_data.is_initialized = true;
+    let counter_data = MovieCommentCounter {
+        discriminator: MovieCommentCounter::DISCRIMINATOR.to_string(),
+        is_initialized: true,
+        counter: 0,
+    };
 
     msg!("comment count: {}", counter_data.counter);
     counter_data.serialize(&mut &mut pda_counter.data.borrow_mut()[..])?;
 
     msg!("Comment counter initialized");
-
-    msg!("serializing account");
-    account_data.serialize(&mut &mut pda_account.data.borrow_mut()[..])?;
-    msg!("state account serialized");
-
-    Ok(())
+    Ok(counter_data)
 }
 
 pub fn update_movie_review(
@@ -179,6 +223,8 @@ pub fn update_movie_review(
     // Get accounts
     let initializer = next_account_info(account_info_iter)?;
     let pda_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let movie_aggregate = next_account_info(account_info_iter)?;
 
     if pda_account.owner != program_id {
         return Err(ProgramError::IllegalOwner);
@@ -194,16 +240,13 @@ pub fn update_movie_review(
         try_from_slice_unchecked::<MovieAccountState>(&pda_account.data.borrow()).unwrap();
     msg!("borrowed account data");
 
-    // Derive PDA and check that it matches client
-    let (pda, _bump_seed) = Pubkey::find_program_address(
-        &[
-            initializer.key.as_ref(),
-            account_data.title.as_bytes().as_ref(),
-        ],
+    // Derive PDA and check that it matches client, legacy title-seeded reviews are accepted too
+    if !pda::is_review_address(
         program_id,
-    );
-
-    if pda != *pda_account.key {
+        initializer.key,
+        &account_data.title,
+        pda_account.key,
+    ) {
         msg!("Invalid seeds for PDA");
         return Err(ReviewError::InvalidPDA.into());
     }
@@ -218,18 +261,157 @@ pub fn update_movie_review(
         return Err(ReviewError::InvalidRating.into());
     }
 
-    let total_len: usize = 1 + 1 + (4 + account_data.title.len()) + (4 + description.len());
-    if total_len > 1000 {
+    let new_len =
+        MovieAccountState::get_account_size(account_data.title.clone(), description.clone());
+    if new_len > MovieAccountState::MAX_SIZE {
         msg!("Data length is larger than 1000 bytes");
         return Err(ReviewError::InvalidDataLength.into());
     }
+
+    // Reviews seeded with the raw title predate the aggregates and were never counted
+    if !pda::is_legacy_review_address(
+        program_id,
+        initializer.key,
+        &account_data.title,
+        pda_account.key,
+    ) {
+        let mut aggregate_data =
+            load_movie_aggregate(program_id, movie_aggregate, &account_data.title)?;
+        aggregate_data.remove_rating(account_data.rating);
+        aggregate_data.add_rating(rating);
+        aggregate_data.serialize(&mut &mut movie_aggregate.data.borrow_mut()[..])?;
+    }
+
     account_data.rating = rating;
     account_data.description = description;
 
+    resize_account(pda_account, initializer, system_program, new_len)?;
+
     account_data.serialize(&mut &mut pda_account.data.borrow_mut()[..])?;
     Ok(())
 }
 
+// The first review of a title pays for its aggregate, later reviews reuse it
+fn load_or_create_movie_aggregate<'a>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'a>,
+    movie_aggregate: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    title: &str,
+) -> Result<MovieAggregate, ProgramError> {
+    if !movie_aggregate.data_is_empty() {
+        return load_movie_aggregate(program_id, movie_aggregate, title);
+    }
+
+    let (pda, bump_seed) = pda::find_movie_address(program_id, title);
+    if pda != *movie_aggregate.key {
+        msg!("Invalid seeds for movie aggregate");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    allocate_pda_account(
+        program_id,
+        payer,
+        movie_aggregate,
+        system_program,
+        MovieAggregate::SIZE,
+        &[pda::MOVIE_SEED, &pda::title_seed(title), &[bump_seed]],
+    )?;
+    msg!("Movie aggregate created: {}", pda);
+
+    Ok(MovieAggregate {
+        discriminator: MovieAggregate::DISCRIMINATOR.to_string(),
+        is_initialized: true,
+        review_count: 0,
+        rating_sum: 0,
+        histogram: [0; 5],
+    })
+}
+
+fn load_movie_aggregate(
+    program_id: &Pubkey,
+    movie_aggregate: &AccountInfo,
+    title: &str,
+) -> Result<MovieAggregate, ProgramError> {
+    if movie_aggregate.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let (pda, _bump_seed) = pda::find_movie_address(program_id, title);
+    if pda != *movie_aggregate.key {
+        msg!("Invalid seeds for movie aggregate");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    let aggregate_data =
+        try_from_slice_unchecked::<MovieAggregate>(&movie_aggregate.data.borrow())?;
+    if !aggregate_data.is_initialized() {
+        msg!("Movie aggregate is not initialized");
+        return Err(ReviewError::UninitializedAccount.into());
+    }
+
+    Ok(aggregate_data)
+}
+
+// Reallocs a program account to `new_len`, with the payer covering any extra
+// rent or getting the difference back when the account shrinks
+fn resize_account<'a>(
+    account: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    new_len: usize,
+) -> ProgramResult {
+    if account.data_len() == new_len {
+        return Ok(());
+    }
+
+    let rent = Rent::get()?;
+    let new_minimum_balance = rent.minimum_balance(new_len);
+    let current_lamports = account.lamports();
+
+    if new_minimum_balance <= current_lamports {
+        return shrink_account(account, payer, new_len);
+    }
+
+    msg!(
+        "Topping up rent by {}",
+        new_minimum_balance - current_lamports
+    );
+    invoke(
+        &system_instruction::transfer(
+            payer.key,
+            account.key,
+            new_minimum_balance - current_lamports,
+        ),
+        &[payer.clone(), account.clone(), system_program.clone()],
+    )?;
+
+    account.realloc(new_len, false)
+}
+
+// Reallocs a program account down to `new_len` and sends the rent it no longer needs to `destination`
+fn shrink_account(
+    account: &AccountInfo,
+    destination: &AccountInfo,
+    new_len: usize,
+) -> ProgramResult {
+    let rent = Rent::get()?;
+    let new_minimum_balance = rent.minimum_balance(new_len);
+    let current_lamports = account.lamports();
+
+    if current_lamports > new_minimum_balance {
+        let refund = current_lamports - new_minimum_balance;
+        msg!("Refunding {} of rent", refund);
+        **account.lamports.borrow_mut() = new_minimum_balance;
+        **destination.lamports.borrow_mut() = destination
+            .lamports()
+            .checked_add(refund)
+            .ok_or(ProgramError::InvalidArgument)?;
+    }
+
+    account.realloc(new_len, false)
+}
+
 pub fn add_comment(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
@@ -245,45 +427,220 @@ pub fn add_comment(
     let pda_counter = next_account_info(account_info_iter)?;
     let pda_comment = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
+    let rewards = RewardAccounts::next(account_info_iter)?;
+
+    // A deleted review's counter outlives it, so the review itself has to be checked
+    if pda_review.owner != program_id || pda_counter.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let (counter, _counter_bump) = pda::find_comment_counter_address(program_id, pda_review.key);
+    if counter != *pda_counter.key {
+        msg!("Invalid seeds for PDA");
+        return Err(ReviewError::InvalidPDA.into());
+    }
 
     let mut counter_data =
         try_from_slice_unchecked::<MovieCommentCounter>(&pda_counter.data.borrow()).unwrap();
 
-    let account_len = MovieComment::get_account_size(comment.clone());
+    write_comment(
+        program_id,
+        commenter,
+        pda_review.key,
+        pda_comment,
+        system_program,
+        pda_review.key,
+        &counter_data,
+        comment,
+        None,
+        counter_data.counter,
+    )?;
 
-    let rent = Rent::get()?;
-    let rent_lamports = rent.minimum_balance(account_len);
+    msg!("Comment Count: {}", counter_data.counter);
+    counter_data.counter += 1;
+    counter_data.serialize(&mut &mut pda_counter.data.borrow_mut()[..])?;
 
-    let (pda, bump_seed) = Pubkey::find_program_address(
+    let (_receipt, receipt_bump) =
+        pda::find_comment_reward_address(program_id, pda_review.key, commenter.key);
+    let comment_reward = load_reward_config(program_id, rewards.token_config)?
+        .map_or(0, |config| config.comment_reward);
+    mint_reward(
+        program_id,
+        commenter,
+        system_program,
+        &rewards,
+        comment_reward,
         &[
+            pda::COMMENT_REWARD_SEED,
             pda_review.key.as_ref(),
-            counter_data.counter.to_be_bytes().as_ref(),
+            commenter.key.as_ref(),
+            &[receipt_bump],
         ],
+    )?;
+
+    Ok(())
+}
+
+pub fn reply_to_comment(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    comment: String,
+) -> ProgramResult {
+    msg!("Replying to comment...");
+    msg!("Reply: {}", comment);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let commenter = next_account_info(account_info_iter)?;
+    let pda_review = next_account_info(account_info_iter)?;
+    let pda_parent = next_account_info(account_info_iter)?;
+    let pda_reply_counter = next_account_info(account_info_iter)?;
+    let pda_reply = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if pda_parent.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let parent_data = MovieComment::try_from_account_data(&pda_parent.data.borrow())?;
+
+    if parent_data.discriminator != MovieComment::DISCRIMINATOR {
+        msg!("Account is not a comment");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !parent_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(ReviewError::UninitializedAccount.into());
+    }
+
+    if pda_review.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let review_data = try_from_slice_unchecked::<MovieAccountState>(&pda_review.data.borrow())?;
+
+    if review_data.discriminator != MovieAccountState::DISCRIMINATOR {
+        msg!("Account is not a review");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !review_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(ReviewError::UninitializedAccount.into());
+    }
+
+    // Threads started before MigrateReview point at the closed legacy address,
+    // the migrated review stands in for it and their replies stay under it
+    let legacy_thread = parent_data.review != *pda_review.key
+        && review_data.legacy_review == Some(parent_data.review);
+
+    if parent_data.review != *pda_review.key && !legacy_thread {
+        msg!("Parent comment belongs to a different review");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    if parent_data.deleted {
+        msg!("Comment has been deleted");
+        return Err(ReviewError::CommentDeleted.into());
+    }
+
+    // Replies are numbered per parent, so a thread is matched to the review by
+    // its top level comment. The legacy address can't get a new review after
+    // migrating, so all of its comments belong to this one
+    if !legacy_thread && parent_data.thread_start() < review_data.first_comment {
+        msg!("Comment was left on an earlier review at this address");
+        return Err(ReviewError::CommentDeleted.into());
+    }
+
+    let (reply_counter, reply_counter_bump) =
+        pda::find_reply_counter_address(program_id, pda_parent.key);
+    if reply_counter != *pda_reply_counter.key {
+        msg!("Invalid seeds for PDA");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    // The reply counter is created by the first reply a comment gets
+    let mut counter_data = if pda_reply_counter.data_is_empty() {
+        allocate_pda_account(
+            program_id,
+            commenter,
+            pda_reply_counter,
+            system_program,
+            MovieCommentCounter::SIZE,
+            &[
+                pda_parent.key.as_ref(),
+                "reply".as_ref(),
+                &[reply_counter_bump],
+            ],
+        )?;
+        MovieCommentCounter {
+            discriminator: MovieCommentCounter::DISCRIMINATOR.to_string(),
+            is_initialized: true,
+            counter: 0,
+        }
+    } else {
+        if pda_reply_counter.owner != program_id {
+            return Err(ProgramError::IllegalOwner);
+        }
+        try_from_slice_unchecked::<MovieCommentCounter>(&pda_reply_counter.data.borrow()).unwrap()
+    };
+
+    write_comment(
         program_id,
-    );
+        commenter,
+        &parent_data.review,
+        pda_reply,
+        system_program,
+        pda_parent.key,
+        &counter_data,
+        comment,
+        Some(*pda_parent.key),
+        parent_data.thread_start(),
+    )?;
+
+    msg!("Reply Count: {}", counter_data.counter);
+    counter_data.counter += 1;
+    counter_data.serialize(&mut &mut pda_reply_counter.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+// Creates the comment numbered `counter_data.counter` under `seed_key`, which is
+// the review for top level comments and the parent comment for replies
+#[allow(clippy::too_many_arguments)]
+fn write_comment<'a>(
+    program_id: &Pubkey,
+    commenter: &AccountInfo<'a>,
+    review: &Pubkey,
+    pda_comment: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    seed_key: &Pubkey,
+    counter_data: &MovieCommentCounter,
+    comment: String,
+    parent: Option<Pubkey>,
+    thread: u64,
+) -> ProgramResult {
+    let account_len = MovieComment::get_account_size(comment.clone());
+
+    let (pda, bump_seed) = pda::Seq::new(program_id, seed_key).find_address(counter_data.counter);
     if pda != *pda_comment.key {
         msg!("Invalid seeds for PDA");
         return Err(ReviewError::InvalidPDA.into());
     }
 
-    invoke_signed(
-        &system_instruction::create_account(
-            commenter.key,
-            pda_comment.key,
-            rent_lamports,
-            account_len.try_into().unwrap(),
-            program_id,
-        ),
+    // A funded next address would otherwise stop the sequence, and every
+    // comment after it, from ever being written
+    allocate_pda_account(
+        program_id,
+        commenter,
+        pda_comment,
+        system_program,
+        account_len,
         &[
-            commenter.clone(),
-            pda_comment.clone(),
-            system_program.clone(),
-        ],
-        &[&[
-            pda_review.key.as_ref(),
+            seed_key.as_ref(),
             counter_data.counter.to_be_bytes().as_ref(),
             &[bump_seed],
-        ]],
+        ],
     )?;
 
     msg!("Created Comment Account");
@@ -298,38 +655,1167 @@ pub fn add_comment(
     }
 
     comment_data.discriminator = MovieComment::DISCRIMINATOR.to_string();
-    comment_data.review = *pda_review.key;
+    comment_data.review = *review;
     comment_data.commenter = *commenter.key;
     comment_data.comment = comment;
+    comment_data.count = counter_data.counter;
+    comment_data.deleted = false;
+    comment_data.parent = parent;
+    comment_data.hidden = false;
+    comment_data.thread = thread;
     comment_data.is_initialized = true;
     comment_data.serialize(&mut &mut pda_comment.data.borrow_mut()[..])?;
-
-    msg!("Comment Count: {}", counter_data.counter);
-    counter_data.counter += 1;
-    counter_data.serialize(&mut &mut pda_counter.data.borrow_mut()[..])?;
     Ok(())
 }
 
-pub fn process_instruction(
+pub fn update_comment(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    instruction_data: &[u8],
+    comment: String,
 ) -> ProgramResult {
-    let instruction = MovieInstruction::unpack(instruction_data)?;
-    match instruction {
-        MovieInstruction::AddMovieReview {
-            title,
-            rating,
-            description,
-        } => add_movie_review(program_id, accounts, title, rating, description),
-        MovieInstruction::UpdateMovieReview {
-            title,
-            rating,
-            description,
-        } => {
-            // make call to update function that we'll define next
-            update_movie_review(program_id, accounts, title, rating, description)
-        }
-        MovieInstruction::AddComment { comment } => add_comment(program_id, accounts, comment),
+    msg!("Updating comment...");
+    msg!("Comment: {}", comment);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let commenter = next_account_info(account_info_iter)?;
+    let pda_comment = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    let mut comment_data = load_own_comment(program_id, commenter, pda_comment)?;
+    comment_data.comment = comment;
+
+    let new_len = MovieComment::get_account_size(comment_data.comment.clone());
+    resize_account(pda_comment, commenter, system_program, new_len)?;
+
+    comment_data.serialize(&mut &mut pda_comment.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn delete_comment(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    msg!("Deleting comment...");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let commenter = next_account_info(account_info_iter)?;
+    let pda_comment = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    let mut comment_data = load_own_comment(program_id, commenter, pda_comment)?;
+
+    // The account can't be closed or the comments after it would no longer be
+    // reachable by walking the counter, so it's shrunk down to a tombstone instead
+    comment_data.comment = String::new();
+    comment_data.deleted = true;
+
+    // Older comments with short text can still need to grow to fit the flags
+    let new_len = MovieComment::get_account_size(comment_data.comment.clone());
+    resize_account(pda_comment, commenter, system_program, new_len)?;
+
+    comment_data.serialize(&mut &mut pda_comment.data.borrow_mut()[..])?;
+    msg!("Comment {} deleted", pda_comment.key);
+
+    Ok(())
+}
+
+// Loads a comment that the signer wrote and hasn't deleted yet
+fn load_own_comment(
+    program_id: &Pubkey,
+    commenter: &AccountInfo,
+    pda_comment: &AccountInfo,
+) -> Result<MovieComment, ProgramError> {
+    if pda_comment.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    if !commenter.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let comment_data = MovieComment::try_from_account_data(&pda_comment.data.borrow())?;
+
+    if comment_data.discriminator != MovieComment::DISCRIMINATOR {
+        msg!("Account is not a comment");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !comment_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(ReviewError::UninitializedAccount.into());
+    }
+
+    if comment_data.commenter != *commenter.key {
+        msg!("Only the commenter can change a comment");
+        return Err(ReviewError::InvalidCommenter.into());
+    }
+
+    if comment_data.deleted {
+        msg!("Comment has been deleted");
+        return Err(ReviewError::CommentDeleted.into());
+    }
+
+    Ok(comment_data)
+}
+
+pub fn delete_movie_review(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    msg!("Deleting movie review...");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let reviewer = next_account_info(account_info_iter)?;
+    let pda_account = next_account_info(account_info_iter)?;
+    let pda_counter = next_account_info(account_info_iter)?;
+    let movie_aggregate = next_account_info(account_info_iter)?;
+
+    if pda_account.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    if !reviewer.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let account_data =
+        try_from_slice_unchecked::<MovieAccountState>(&pda_account.data.borrow()).unwrap();
+
+    if !account_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(ReviewError::UninitializedAccount.into());
+    }
+
+    if account_data.reviewer != *reviewer.key {
+        msg!("Only the reviewer can delete a review");
+        return Err(ReviewError::InvalidReviewer.into());
+    }
+
+    if !pda::is_review_address(
+        program_id,
+        reviewer.key,
+        &account_data.title,
+        pda_account.key,
+    ) {
+        msg!("Invalid seeds for PDA");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    let (counter, _counter_bump) = pda::find_comment_counter_address(program_id, pda_account.key);
+    if counter != *pda_counter.key {
+        msg!("Invalid seeds for PDA");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    if !pda::is_legacy_review_address(
+        program_id,
+        reviewer.key,
+        &account_data.title,
+        pda_account.key,
+    ) {
+        let mut aggregate_data =
+            load_movie_aggregate(program_id, movie_aggregate, &account_data.title)?;
+        aggregate_data.remove_rating(account_data.rating);
+        aggregate_data.serialize(&mut &mut movie_aggregate.data.borrow_mut()[..])?;
+    }
+
+    // Comment accounts are left in place, their `review` field points at a closed
+    // account until a review is added at the same address again. The counter is
+    // kept so that review numbers its comments after the orphaned ones and can
+    // tell them apart by `first_comment`
+    close_account(pda_account, reviewer)?;
+    msg!("Review {} deleted", pda_account.key);
+
+    Ok(())
+}
+
+// Moves a review seeded with the raw title to its hashed address and counts it
+// in the movie aggregate. Comments can't be moved since their addresses are
+// seeded with the review, they stay under the legacy address and its counter,
+// which the migrated review links to. Replies to them go through the migrated
+// review and are written under the legacy address like the rest of the thread
+pub fn migrate_review(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    msg!("Migrating movie review...");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let reviewer = next_account_info(account_info_iter)?;
+    let legacy_account = next_account_info(account_info_iter)?;
+    let pda_account = next_account_info(account_info_iter)?;
+    let pda_counter = next_account_info(account_info_iter)?;
+    let movie_aggregate = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if legacy_account.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    if !reviewer.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mut account_data =
+        try_from_slice_unchecked::<MovieAccountState>(&legacy_account.data.borrow())?;
+
+    if !account_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(ReviewError::UninitializedAccount.into());
+    }
+
+    if account_data.reviewer != *reviewer.key {
+        msg!("Only the reviewer can migrate a review");
+        return Err(ReviewError::InvalidReviewer.into());
+    }
+
+    if !pda::is_legacy_review_address(
+        program_id,
+        reviewer.key,
+        &account_data.title,
+        legacy_account.key,
+    ) {
+        msg!("Review is not at a legacy address");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    let (pda, bump_seed) = pda::find_review_address(program_id, reviewer.key, &account_data.title);
+    if pda != *pda_account.key {
+        msg!("Invalid seeds for PDA");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    let account_len = MovieAccountState::get_account_size(
+        account_data.title.clone(),
+        account_data.description.clone(),
+    );
+    allocate_pda_account(
+        program_id,
+        reviewer,
+        pda_account,
+        system_program,
+        account_len,
+        &[
+            reviewer.key.as_ref(),
+            &pda::title_seed(&account_data.title),
+            &[bump_seed],
+        ],
+    )?;
+
+    let counter_data = load_or_create_comment_counter(
+        program_id,
+        reviewer,
+        pda_account,
+        pda_counter,
+        system_program,
+    )?;
+
+    // Legacy reviews were never counted, so it's added rather than moved
+    let mut aggregate_data = load_or_create_movie_aggregate(
+        program_id,
+        reviewer,
+        movie_aggregate,
+        system_program,
+        &account_data.title,
+    )?;
+    aggregate_data.add_rating(account_data.rating);
+    aggregate_data.serialize(&mut &mut movie_aggregate.data.borrow_mut()[..])?;
+
+    account_data.first_comment = counter_data.counter;
+    account_data.legacy_review = Some(*legacy_account.key);
+    account_data.serialize(&mut &mut pda_account.data.borrow_mut()[..])?;
+
+    close_account(legacy_account, reviewer)?;
+    msg!(
+        "Review {} migrated to {}",
+        legacy_account.key,
+        pda_account.key
+    );
+
+    Ok(())
+}
+
+pub fn vote_on_review(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    upvote: bool,
+) -> ProgramResult {
+    msg!("Voting on review...");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let voter = next_account_info(account_info_iter)?;
+    let pda_review = next_account_info(account_info_iter)?;
+    let pda_score = next_account_info(account_info_iter)?;
+    let pda_receipt = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !voter.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if pda_review.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let review_data = try_from_slice_unchecked::<MovieAccountState>(&pda_review.data.borrow())?;
+
+    if review_data.discriminator != MovieAccountState::DISCRIMINATOR {
+        msg!("Account is not a review");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !review_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(ReviewError::UninitializedAccount.into());
+    }
+
+    // The score and receipts are seeded with the review address and outlive
+    // DeleteMovieReview, so a review added again keeps its votes. That's on
+    // purpose, clearing only the score would let the old receipts take back
+    // votes it no longer counts
+    let (score, score_bump) = pda::find_review_score_address(program_id, pda_review.key);
+    if score != *pda_score.key {
+        msg!("Invalid seeds for PDA");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    let (receipt, receipt_bump) =
+        pda::find_vote_receipt_address(program_id, pda_review.key, voter.key);
+    if receipt != *pda_receipt.key {
+        msg!("Invalid seeds for PDA");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    // The score account is created by the first vote a review gets
+    let mut score_data = if pda_score.data_is_empty() {
+        allocate_pda_account(
+            program_id,
+            voter,
+            pda_score,
+            system_program,
+            ReviewScore::SIZE,
+            &[pda_review.key.as_ref(), "score".as_ref(), &[score_bump]],
+        )?;
+        ReviewScore {
+            discriminator: ReviewScore::DISCRIMINATOR.to_string(),
+            is_initialized: true,
+            review: *pda_review.key,
+            upvotes: 0,
+            downvotes: 0,
+        }
+    } else {
+        if pda_score.owner != program_id {
+            return Err(ProgramError::IllegalOwner);
+        }
+        try_from_slice_unchecked::<ReviewScore>(&pda_score.data.borrow()).unwrap()
+    };
+
+    if pda_receipt.data_is_empty() {
+        allocate_pda_account(
+            program_id,
+            voter,
+            pda_receipt,
+            system_program,
+            VoteReceipt::SIZE,
+            &[pda_review.key.as_ref(), voter.key.as_ref(), &[receipt_bump]],
+        )?;
+    } else {
+        if pda_receipt.owner != program_id {
+            return Err(ProgramError::IllegalOwner);
+        }
+        let receipt_data =
+            try_from_slice_unchecked::<VoteReceipt>(&pda_receipt.data.borrow()).unwrap();
+
+        if receipt_data.upvote == upvote {
+            msg!("Vote unchanged");
+            return Ok(());
+        }
+
+        // Changing a vote takes back the previous one first
+        msg!("Changing vote");
+        if receipt_data.upvote {
+            score_data.upvotes = score_data.upvotes.saturating_sub(1);
+        } else {
+            score_data.downvotes = score_data.downvotes.saturating_sub(1);
+        }
+    }
+
+    if upvote {
+        score_data.upvotes += 1;
+    } else {
+        score_data.downvotes += 1;
+    }
+
+    let receipt_data = VoteReceipt {
+        discriminator: VoteReceipt::DISCRIMINATOR.to_string(),
+        is_initialized: true,
+        review: *pda_review.key,
+        voter: *voter.key,
+        upvote,
+    };
+    receipt_data.serialize(&mut &mut pda_receipt.data.borrow_mut()[..])?;
+
+    msg!(
+        "Upvotes: {}, downvotes: {}",
+        score_data.upvotes,
+        score_data.downvotes
+    );
+    score_data.serialize(&mut &mut pda_score.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+// Creates a PDA owned by `owner`. Anyone can send lamports to an address before
+// it exists, which makes create_account fail, so an address that already holds
+// lamports is topped up to rent and allocated and assigned instead
+fn allocate_pda_account<'a>(
+    owner: &Pubkey,
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    size: usize,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    let rent_lamports = Rent::get()?.minimum_balance(size);
+
+    if account.lamports() == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                account.key,
+                rent_lamports,
+                size.try_into().unwrap(),
+                owner,
+            ),
+            &[payer.clone(), account.clone(), system_program.clone()],
+            &[seeds],
+        );
+    }
+
+    let shortfall = rent_lamports.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, shortfall),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+
+    invoke_signed(
+        &system_instruction::allocate(account.key, size.try_into().unwrap()),
+        &[account.clone(), system_program.clone()],
+        &[seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, owner),
+        &[account.clone(), system_program.clone()],
+        &[seeds],
+    )
+}
+
+pub fn initialize_token_mint(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    review_reward: u64,
+    comment_reward: u64,
+) -> ProgramResult {
+    msg!("Initializing token mint...");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let initializer = next_account_info(account_info_iter)?;
+    let token_config = next_account_info(account_info_iter)?;
+    let token_mint = next_account_info(account_info_iter)?;
+    let mint_auth = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let sysvar_rent = next_account_info(account_info_iter)?;
+    let program_data = next_account_info(account_info_iter)?;
+
+    // The rewards can be changed later, but only by the admin this sets up
+    check_upgrade_authority(program_id, program_data, initializer)?;
+
+    if *token_program.key != spl_token::id() {
+        msg!("Incorrect token program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let (config_pda, config_bump) = pda::find_token_config_address(program_id);
+    let (mint_pda, mint_bump) = pda::find_token_mint_address(program_id);
+    let (mint_auth_pda, _mint_auth_bump) = pda::find_mint_auth_address(program_id);
+
+    if config_pda != *token_config.key
+        || mint_pda != *token_mint.key
+        || mint_auth_pda != *mint_auth.key
+    {
+        msg!("Invalid seeds for PDA");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    // Creating the config fails if it already exists, so this can only run once
+    allocate_pda_account(
+        program_id,
+        initializer,
+        token_config,
+        system_program,
+        TokenConfig::SIZE,
+        &[pda::TOKEN_CONFIG_SEED, &[config_bump]],
+    )?;
+
+    allocate_pda_account(
+        token_program.key,
+        initializer,
+        token_mint,
+        system_program,
+        Mint::LEN,
+        &[pda::TOKEN_MINT_SEED, &[mint_bump]],
+    )?;
+    msg!("Created token mint account");
+
+    invoke(
+        &initialize_mint(
+            token_program.key,
+            token_mint.key,
+            mint_auth.key,
+            None,
+            TokenConfig::DECIMALS,
+        )?,
+        &[token_mint.clone(), sysvar_rent.clone(), mint_auth.clone()],
+    )?;
+    msg!("Initialized token mint");
+
+    let config_data = TokenConfig {
+        discriminator: TokenConfig::DISCRIMINATOR.to_string(),
+        is_initialized: true,
+        admin: *initializer.key,
+        mint: *token_mint.key,
+        review_reward,
+        comment_reward,
+    };
+    config_data.serialize(&mut &mut token_config.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+pub fn update_token_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    admin: Pubkey,
+    review_reward: u64,
+    comment_reward: u64,
+) -> ProgramResult {
+    msg!("Updating token config...");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let signer = next_account_info(account_info_iter)?;
+    let token_config = next_account_info(account_info_iter)?;
+
+    if !signer.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mut config_data = load_token_config(program_id, token_config)?;
+    if config_data.admin != *signer.key {
+        msg!("Only the admin can change the token config");
+        return Err(ReviewError::InvalidAdmin.into());
+    }
+
+    config_data.admin = admin;
+    config_data.review_reward = review_reward;
+    config_data.comment_reward = comment_reward;
+    config_data.serialize(&mut &mut token_config.data.borrow_mut()[..])?;
+
+    msg!(
+        "EVENT token_config_updated admin={} review_reward={} comment_reward={}",
+        admin,
+        review_reward,
+        comment_reward
+    );
+
+    Ok(())
+}
+
+// One time setup is reserved for the upgrade authority recorded in the
+// program's ProgramData account, so it can't be claimed by whoever calls first
+fn check_upgrade_authority(
+    program_id: &Pubkey,
+    program_data: &AccountInfo,
+    authority: &AccountInfo,
+) -> ProgramResult {
+    if !authority.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (program_data_address, _bump) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    if program_data_address != *program_data.key
+        || *program_data.owner != bpf_loader_upgradeable::id()
+    {
+        msg!("Invalid program data account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let state = limited_deserialize::<UpgradeableLoaderState>(
+        &program_data.data.borrow(),
+        UpgradeableLoaderState::size_of_programdata_metadata() as u64,
+    )
+    .map_err(|_| ProgramError::InvalidAccountData)?;
+
+    match state {
+        UpgradeableLoaderState::ProgramData {
+            upgrade_authority_address: Some(upgrade_authority),
+            ..
+        } if upgrade_authority == *authority.key => Ok(()),
+        _ => {
+            msg!("Signer is not the upgrade authority");
+            Err(ReviewError::InvalidAdmin.into())
+        }
+    }
+}
+
+// Accounts for paying out engagement rewards, they follow the accounts of the rewarded instruction
+struct RewardAccounts<'a, 'b> {
+    token_config: &'b AccountInfo<'a>,
+    token_mint: &'b AccountInfo<'a>,
+    mint_auth: &'b AccountInfo<'a>,
+    author_token_account: &'b AccountInfo<'a>,
+    token_program: &'b AccountInfo<'a>,
+    reward_receipt: &'b AccountInfo<'a>,
+}
+
+impl<'a, 'b> RewardAccounts<'a, 'b> {
+    fn next<I: Iterator<Item = &'b AccountInfo<'a>>>(iter: &mut I) -> Result<Self, ProgramError> {
+        Ok(Self {
+            token_config: next_account_info(iter)?,
+            token_mint: next_account_info(iter)?,
+            mint_auth: next_account_info(iter)?,
+            author_token_account: next_account_info(iter)?,
+            token_program: next_account_info(iter)?,
+            reward_receipt: next_account_info(iter)?,
+        })
+    }
+}
+
+fn load_token_config(
+    program_id: &Pubkey,
+    token_config: &AccountInfo,
+) -> Result<TokenConfig, ProgramError> {
+    if token_config.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let (config_pda, _config_bump) = pda::find_token_config_address(program_id);
+    if config_pda != *token_config.key {
+        msg!("Invalid seeds for PDA");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    let config_data = try_from_slice_unchecked::<TokenConfig>(&token_config.data.borrow())?;
+    if !config_data.is_initialized() {
+        msg!("Token config is not initialized");
+        return Err(ReviewError::UninitializedAccount.into());
+    }
+
+    Ok(config_data)
+}
+
+// Rewards are off until InitializeTokenMint has created the config, posting
+// reviews and comments doesn't wait on it
+fn load_reward_config(
+    program_id: &Pubkey,
+    token_config: &AccountInfo,
+) -> Result<Option<TokenConfig>, ProgramError> {
+    let (config_pda, _config_bump) = pda::find_token_config_address(program_id);
+    if config_pda != *token_config.key {
+        msg!("Invalid seeds for PDA");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    if token_config.owner != program_id {
+        return Ok(None);
+    }
+
+    let config_data = try_from_slice_unchecked::<TokenConfig>(&token_config.data.borrow())?;
+    if !config_data.is_initialized() {
+        return Ok(None);
+    }
+
+    Ok(Some(config_data))
+}
+
+// Mints `amount` reward tokens to a token account of `author`, signed by the mint
+// authority PDA. The first payout creates the receipt at `receipt_seeds`, later
+// calls with the same receipt mint nothing. The token accounts are only checked
+// when something gets minted, so an author without one can still post
+fn mint_reward<'a>(
+    program_id: &Pubkey,
+    author: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    rewards: &RewardAccounts<'a, '_>,
+    amount: u64,
+    receipt_seeds: &[&[u8]],
+) -> ProgramResult {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    let receipt = Pubkey::create_program_address(receipt_seeds, program_id)
+        .map_err(|_| ReviewError::InvalidPDA)?;
+    if receipt != *rewards.reward_receipt.key {
+        msg!("Invalid seeds for PDA");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    if rewards.reward_receipt.owner == program_id {
+        msg!("Reward already paid out");
+        return Ok(());
+    }
+
+    if *rewards.token_program.key != spl_token::id() {
+        msg!("Incorrect token program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let (mint_pda, _mint_bump) = pda::find_token_mint_address(program_id);
+    let (mint_auth_pda, mint_auth_bump) = pda::find_mint_auth_address(program_id);
+    if mint_pda != *rewards.token_mint.key || mint_auth_pda != *rewards.mint_auth.key {
+        msg!("Invalid seeds for PDA");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    if *rewards.author_token_account.owner != spl_token::id() {
+        return Err(ReviewError::InvalidTokenAccount.into());
+    }
+    let token_account = Account::unpack(&rewards.author_token_account.data.borrow())?;
+    if token_account.owner != *author.key || token_account.mint != mint_pda {
+        msg!("Token account does not belong to the author");
+        return Err(ReviewError::InvalidTokenAccount.into());
+    }
+
+    allocate_pda_account(
+        program_id,
+        author,
+        rewards.reward_receipt,
+        system_program,
+        RewardReceipt::SIZE,
+        receipt_seeds,
+    )?;
+    let receipt_data = RewardReceipt {
+        discriminator: RewardReceipt::DISCRIMINATOR.to_string(),
+        is_initialized: true,
+        recipient: *author.key,
+        amount,
+    };
+    receipt_data.serialize(&mut &mut rewards.reward_receipt.data.borrow_mut()[..])?;
+
+    msg!("Minting {} reward tokens to {}", amount, author.key);
+    invoke_signed(
+        &mint_to(
+            rewards.token_program.key,
+            rewards.token_mint.key,
+            rewards.author_token_account.key,
+            rewards.mint_auth.key,
+            &[],
+            amount,
+        )?,
+        &[
+            rewards.token_mint.clone(),
+            rewards.author_token_account.clone(),
+            rewards.mint_auth.clone(),
+        ],
+        &[&[pda::MINT_AUTH_SEED, &[mint_auth_bump]]],
+    )
+}
+
+pub fn tip_reviewer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+    currency: TipCurrency,
+) -> ProgramResult {
+    msg!("Tipping reviewer...");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let tipper = next_account_info(account_info_iter)?;
+    let pda_review = next_account_info(account_info_iter)?;
+    let reviewer = next_account_info(account_info_iter)?;
+    let pda_tip_stats = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !tipper.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if amount == 0 {
+        msg!("Tip amount must be greater than zero");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    if pda_review.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let review_data =
+        try_from_slice_unchecked::<MovieAccountState>(&pda_review.data.borrow()).unwrap();
+
+    if review_data.discriminator != MovieAccountState::DISCRIMINATOR {
+        msg!("Account is not a review");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !review_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(ReviewError::UninitializedAccount.into());
+    }
+
+    if review_data.reviewer != *reviewer.key {
+        msg!("Tips can only be sent to the reviewer");
+        return Err(ReviewError::InvalidTipDestination.into());
+    }
+
+    let (tip_stats, tip_stats_bump) = pda::find_tip_stats_address(program_id, pda_review.key);
+    if tip_stats != *pda_tip_stats.key {
+        msg!("Invalid seeds for PDA");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    match currency {
+        TipCurrency::Sol => {
+            invoke(
+                &system_instruction::transfer(tipper.key, reviewer.key, amount),
+                &[tipper.clone(), reviewer.clone(), system_program.clone()],
+            )?;
+        }
+        TipCurrency::RewardToken => {
+            let tipper_token_account = next_account_info(account_info_iter)?;
+            let reviewer_token_account = next_account_info(account_info_iter)?;
+            let token_program = next_account_info(account_info_iter)?;
+
+            if *token_program.key != spl_token::id() {
+                msg!("Incorrect token program");
+                return Err(ProgramError::IncorrectProgramId);
+            }
+
+            // The token program checks the source, only the destination needs checking here
+            if *reviewer_token_account.owner != spl_token::id() {
+                return Err(ReviewError::InvalidTipDestination.into());
+            }
+            let (mint_pda, _mint_bump) = pda::find_token_mint_address(program_id);
+            let token_account = Account::unpack(&reviewer_token_account.data.borrow())?;
+            if token_account.owner != review_data.reviewer || token_account.mint != mint_pda {
+                msg!("Tips can only be sent to the reviewer");
+                return Err(ReviewError::InvalidTipDestination.into());
+            }
+
+            invoke(
+                &transfer(
+                    token_program.key,
+                    tipper_token_account.key,
+                    reviewer_token_account.key,
+                    tipper.key,
+                    &[],
+                    amount,
+                )?,
+                &[
+                    tipper_token_account.clone(),
+                    reviewer_token_account.clone(),
+                    tipper.clone(),
+                ],
+            )?;
+        }
+    }
+
+    // Tip stats are created by the first tip a review gets
+    let mut stats_data = if pda_tip_stats.data_is_empty() {
+        allocate_pda_account(
+            program_id,
+            tipper,
+            pda_tip_stats,
+            system_program,
+            TipStats::SIZE,
+            &[pda_review.key.as_ref(), "tips".as_ref(), &[tip_stats_bump]],
+        )?;
+        TipStats {
+            discriminator: TipStats::DISCRIMINATOR.to_string(),
+            is_initialized: true,
+            review: *pda_review.key,
+            reviewer: review_data.reviewer,
+            tip_count: 0,
+            lamports_tipped: 0,
+            tokens_tipped: 0,
+        }
+    } else {
+        if pda_tip_stats.owner != program_id {
+            return Err(ProgramError::IllegalOwner);
+        }
+        try_from_slice_unchecked::<TipStats>(&pda_tip_stats.data.borrow()).unwrap()
+    };
+
+    stats_data.tip_count += 1;
+    let total = match currency {
+        TipCurrency::Sol => &mut stats_data.lamports_tipped,
+        TipCurrency::RewardToken => &mut stats_data.tokens_tipped,
+    };
+    *total = total
+        .checked_add(amount)
+        .ok_or(ProgramError::InvalidArgument)?;
+
+    msg!(
+        "Tipped {} {:?} to {}",
+        amount,
+        currency,
+        review_data.reviewer
+    );
+    stats_data.serialize(&mut &mut pda_tip_stats.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+// The first call creates the config and names the moderator, after that only
+// the current moderator can hand the role to someone else
+pub fn set_moderator(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    moderator: Pubkey,
+) -> ProgramResult {
+    msg!("Setting moderator...");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let signer = next_account_info(account_info_iter)?;
+    let moderator_config = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !signer.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (config_pda, config_bump) = pda::find_moderator_config_address(program_id);
+    if config_pda != *moderator_config.key {
+        msg!("Invalid seeds for PDA");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    // The first moderator is named by the upgrade authority, whose ProgramData
+    // account follows, after that only the current moderator can hand over
+    if moderator_config.data_is_empty() {
+        let program_data = next_account_info(account_info_iter)?;
+        check_upgrade_authority(program_id, program_data, signer)?;
+
+        allocate_pda_account(
+            program_id,
+            signer,
+            moderator_config,
+            system_program,
+            ModeratorConfig::SIZE,
+            &[pda::MODERATOR_SEED, &[config_bump]],
+        )?;
+    } else {
+        load_moderator_config(program_id, signer, moderator_config)?;
+    }
+
+    let config_data = ModeratorConfig {
+        discriminator: ModeratorConfig::DISCRIMINATOR.to_string(),
+        is_initialized: true,
+        moderator,
+    };
+    config_data.serialize(&mut &mut moderator_config.data.borrow_mut()[..])?;
+    msg!("Moderator: {}", moderator);
+
+    Ok(())
+}
+
+pub fn set_review_hidden(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    hidden: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let moderator = next_account_info(account_info_iter)?;
+    let moderator_config = next_account_info(account_info_iter)?;
+    let pda_review = next_account_info(account_info_iter)?;
+
+    load_moderator_config(program_id, moderator, moderator_config)?;
+
+    if pda_review.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let mut review_data =
+        try_from_slice_unchecked::<MovieAccountState>(&pda_review.data.borrow()).unwrap();
+
+    if review_data.discriminator != MovieAccountState::DISCRIMINATOR {
+        msg!("Account is not a review");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !review_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(ReviewError::UninitializedAccount.into());
+    }
+
+    review_data.hidden = hidden;
+    review_data.serialize(&mut &mut pda_review.data.borrow_mut()[..])?;
+
+    // Stable format so indexers can filter on it
+    msg!(
+        "EVENT review_hidden review={} hidden={} moderator={}",
+        pda_review.key,
+        hidden,
+        moderator.key
+    );
+
+    Ok(())
+}
+
+pub fn hide_comment(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let moderator = next_account_info(account_info_iter)?;
+    let moderator_config = next_account_info(account_info_iter)?;
+    let pda_comment = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    load_moderator_config(program_id, moderator, moderator_config)?;
+
+    if pda_comment.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let mut comment_data = MovieComment::try_from_account_data(&pda_comment.data.borrow())?;
+
+    if comment_data.discriminator != MovieComment::DISCRIMINATOR {
+        msg!("Account is not a comment");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !comment_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(ReviewError::UninitializedAccount.into());
+    }
+
+    // Older comments have no room for the flag, the moderator pays to grow them
+    let new_len = MovieComment::get_account_size(comment_data.comment.clone());
+    if pda_comment.data_len() < new_len {
+        resize_account(pda_comment, moderator, system_program, new_len)?;
+    }
+
+    comment_data.hidden = true;
+    comment_data.serialize(&mut &mut pda_comment.data.borrow_mut()[..])?;
+
+    msg!(
+        "EVENT comment_hidden comment={} hidden=true moderator={}",
+        pda_comment.key,
+        moderator.key
+    );
+
+    Ok(())
+}
+
+// Checks that `moderator` signed and is the one named in the config
+fn load_moderator_config(
+    program_id: &Pubkey,
+    moderator: &AccountInfo,
+    moderator_config: &AccountInfo,
+) -> Result<ModeratorConfig, ProgramError> {
+    if !moderator.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if moderator_config.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let (config_pda, _config_bump) = pda::find_moderator_config_address(program_id);
+    if config_pda != *moderator_config.key {
+        msg!("Invalid seeds for PDA");
+        return Err(ReviewError::InvalidPDA.into());
+    }
+
+    let config_data = try_from_slice_unchecked::<ModeratorConfig>(&moderator_config.data.borrow())?;
+    if !config_data.is_initialized() {
+        msg!("Moderator config is not initialized");
+        return Err(ReviewError::UninitializedAccount.into());
+    }
+
+    if config_data.moderator != *moderator.key {
+        msg!("Only the moderator can do this");
+        return Err(ReviewError::InvalidModerator.into());
+    }
+
+    Ok(config_data)
+}
+
+// Refunds the rent of a program account and wipes it, handing it back to the
+// system program so it can't be revived as a program account
+fn close_account(account: &AccountInfo, destination: &AccountInfo) -> ProgramResult {
+    let destination_lamports = destination.lamports();
+    **destination.lamports.borrow_mut() = destination_lamports
+        .checked_add(account.lamports())
+        .ok_or(ProgramError::InvalidArgument)?;
+    **account.lamports.borrow_mut() = 0;
+
+    account.data.borrow_mut().fill(0);
+    account.realloc(0, false)?;
+    account.assign(&system_program::id());
+    Ok(())
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let instruction = MovieInstruction::unpack(instruction_data)?;
+    match instruction {
+        MovieInstruction::AddMovieReview {
+            title,
+            rating,
+            description,
+        } => add_movie_review(program_id, accounts, title, rating, description),
+        MovieInstruction::UpdateMovieReview {
+            title,
+            rating,
+            description,
+        } => {
+            // make call to update function that we'll define next
+            update_movie_review(program_id, accounts, title, rating, description)
+        }
+        MovieInstruction::AddComment { comment } => add_comment(program_id, accounts, comment),
+        MovieInstruction::DeleteMovieReview => delete_movie_review(program_id, accounts),
+        MovieInstruction::VoteOnReview { upvote } => vote_on_review(program_id, accounts, upvote),
+        MovieInstruction::UpdateComment { comment } => {
+            update_comment(program_id, accounts, comment)
+        }
+        MovieInstruction::DeleteComment => delete_comment(program_id, accounts),
+        MovieInstruction::ReplyToComment { comment } => {
+            reply_to_comment(program_id, accounts, comment)
+        }
+        MovieInstruction::InitializeTokenMint {
+            review_reward,
+            comment_reward,
+        } => initialize_token_mint(program_id, accounts, review_reward, comment_reward),
+        MovieInstruction::SetModerator { moderator } => {
+            set_moderator(program_id, accounts, moderator)
+        }
+        MovieInstruction::HideReview => set_review_hidden(program_id, accounts, true),
+        MovieInstruction::UnhideReview => set_review_hidden(program_id, accounts, false),
+        MovieInstruction::HideComment => hide_comment(program_id, accounts),
+        MovieInstruction::TipReviewer { amount, currency } => {
+            tip_reviewer(program_id, accounts, amount, currency)
+        }
+        MovieInstruction::MigrateReview => migrate_review(program_id, accounts),
+        MovieInstruction::UpdateTokenConfig {
+            admin,
+            review_reward,
+            comment_reward,
+        } => update_token_config(program_id, accounts, admin, review_reward, comment_reward),
     }
 }
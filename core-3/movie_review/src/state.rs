@@ -1,5 +1,6 @@
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
+    program_error::ProgramError,
     program_pack::{IsInitialized, Sealed},
     pubkey::Pubkey,
 };
@@ -12,9 +13,47 @@ pub struct MovieAccountState {
     pub rating: u8,
     pub title: String,
     pub description: String,
+    // Set by the moderator, frontends shouldn't show hidden reviews
+    pub hidden: bool,
+    // Comments numbered below this were left on an earlier review at the same address
+    pub first_comment: u64,
+    // Set by MigrateReview, comments made before the migration stay numbered under this address
+    pub legacy_review: Option<Pubkey>,
 }
 
-// New struct for recording how many comments total
+// Helpfulness votes on a review, kept in its own account so the review layout stays as is
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ReviewScore {
+    pub discriminator: String,
+    pub is_initialized: bool,
+    pub review: Pubkey,
+    pub upvotes: u64,
+    pub downvotes: u64,
+}
+
+// One per (review, voter), it's what stops a wallet from voting twice
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct VoteReceipt {
+    pub discriminator: String,
+    pub is_initialized: bool,
+    pub review: Pubkey,
+    pub voter: Pubkey,
+    pub upvote: bool,
+}
+
+// Running tip totals for a review, `reviewer` is kept so earners can be ranked without joins
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct TipStats {
+    pub discriminator: String,
+    pub is_initialized: bool,
+    pub review: Pubkey,
+    pub reviewer: Pubkey,
+    pub tip_count: u64,
+    pub lamports_tipped: u64,
+    pub tokens_tipped: u64,
+}
+
+// New struct for recording how many comments total, also used per comment to count its replies
 #[derive(BorshSerialize, BorshDeserialize)]
 pub struct MovieCommentCounter {
     pub discriminator: String,
@@ -31,22 +70,105 @@ pub struct MovieComment {
     pub commenter: Pubkey,
     pub comment: String,
     pub count: u64,
+    // Deleted comments keep their slot in the sequence with the text cleared
+    pub deleted: bool,
+    // Set on replies to the comment they answer, top level comments have none
+    pub parent: Option<Pubkey>,
+    pub hidden: bool,
+    // Count of the top level comment the thread starts at, read it through thread_start
+    pub thread: u64,
+}
+
+// Running totals for every review of a movie, keyed by the normalized title
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct MovieAggregate {
+    pub discriminator: String,
+    pub is_initialized: bool,
+    pub review_count: u64,
+    pub rating_sum: u64,
+    // Number of reviews per rating, index 0 is 1 star
+    pub histogram: [u64; 5],
 }
 
+// Names the moderator allowed to hide reviews and comments
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ModeratorConfig {
+    pub discriminator: String,
+    pub is_initialized: bool,
+    pub moderator: Pubkey,
+}
+
+// Engagement reward settings, created by InitializeTokenMint and changed by the admin
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct TokenConfig {
+    pub discriminator: String,
+    pub is_initialized: bool,
+    pub admin: Pubkey,
+    pub mint: Pubkey,
+    // Base units minted per review and per comment
+    pub review_reward: u64,
+    pub comment_reward: u64,
+}
+
+// Marks a review or comment reward as paid out
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct RewardReceipt {
+    pub discriminator: String,
+    pub is_initialized: bool,
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
 
 impl MovieAccountState {
     pub const DISCRIMINATOR: &'static str = "review";
+    // Upper bound on a review account, accounts are sized to their content below this
+    pub const MAX_SIZE: usize = 1000;
 
     pub fn get_account_size(title: String, description: String) -> usize {
                 // 4 bytes to store the size of the subsequent dynamic data (string)
         return (4 + MovieAccountState::DISCRIMINATOR.len())  
             + 1 // 1 byte for is_initialized (boolean)
+            + 32 // 32 bytes for the reviewer key
             + 1 // 1 byte for rating 
             + (4 + title.len()) // 4 bytes to store the size of the subsequent dynamic data (string)
-            + (4 + description.len()); // Same as above
+            + (4 + description.len()) // Same as above
+            + 1 // 1 byte for hidden (boolean)
+            + 8 // 8 bytes for first_comment (u64)
+            + (1 + 32); // 1 byte for the option tag plus 32 bytes for the legacy review key
     }
 }
 
+impl ReviewScore {
+    pub const DISCRIMINATOR: &'static str = "score";
+    pub const SIZE: usize = (4 + ReviewScore::DISCRIMINATOR.len()) + 1 + 32 + 8 + 8;
+}
+
+impl VoteReceipt {
+    pub const DISCRIMINATOR: &'static str = "vote";
+    pub const SIZE: usize = (4 + VoteReceipt::DISCRIMINATOR.len()) + 1 + 32 + 32 + 1;
+}
+
+impl ModeratorConfig {
+    pub const DISCRIMINATOR: &'static str = "moderator";
+    pub const SIZE: usize = (4 + ModeratorConfig::DISCRIMINATOR.len()) + 1 + 32;
+}
+
+impl TokenConfig {
+    pub const DISCRIMINATOR: &'static str = "token_config";
+    pub const SIZE: usize = (4 + TokenConfig::DISCRIMINATOR.len()) + 1 + 32 + 32 + 8 + 8;
+    pub const DECIMALS: u8 = 6;
+}
+
+impl RewardReceipt {
+    pub const DISCRIMINATOR: &'static str = "reward";
+    pub const SIZE: usize = (4 + RewardReceipt::DISCRIMINATOR.len()) + 1 + 32 + 8;
+}
+
+impl TipStats {
+    pub const DISCRIMINATOR: &'static str = "tips";
+    pub const SIZE: usize = (4 + TipStats::DISCRIMINATOR.len()) + 1 + 32 + 32 + 8 + 8 + 8;
+}
+
 impl MovieComment {
     pub const DISCRIMINATOR: &'static str = "comment";
 
@@ -56,21 +178,118 @@ impl MovieComment {
         + 32 // 32 bytes for the movie review account key 
         + 32 // 32 bytes for the commenter key size
         + (4 + comment.len()) // 4 bytes to store the size of the subsequent dynamic data (string)
-        + 8; // 8 bytes for the count (u64)
+        + 8 // 8 bytes for the count (u64)
+        + 1 // 1 byte for the deleted flag (boolean)
+        + (1 + 32) // 1 byte for the option tag plus 32 bytes for the parent comment key
+        + 1 // 1 byte for hidden (boolean)
+        + 8; // 8 bytes for the thread (u64)
+    }
+
+    // Comments from before `deleted`, `parent`, `hidden` and `thread` were added are
+    // sized exactly to the older fields, those read as not deleted, top level and visible
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        let buf = &mut &data[..];
+        Ok(MovieComment {
+            discriminator: read_field(buf)?,
+            is_initialized: read_field(buf)?,
+            review: read_field(buf)?,
+            commenter: read_field(buf)?,
+            comment: read_field(buf)?,
+            count: read_field(buf)?,
+            deleted: read_trailing_field(buf)?,
+            parent: read_trailing_field(buf)?,
+            hidden: read_trailing_field(buf)?,
+            thread: read_trailing_field(buf)?,
+        })
+    }
+
+    // Older top level comments have no `thread`, they start their own
+    pub fn thread_start(&self) -> u64 {
+        match self.parent {
+            Some(_) => self.thread,
+            None => self.count,
+        }
     }
 }
 
+fn read_field<T: BorshDeserialize>(buf: &mut &[u8]) -> Result<T, ProgramError> {
+    T::deserialize(buf).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+// Fields appended to a layout default when the account ends before them
+fn read_trailing_field<T: BorshDeserialize + Default>(buf: &mut &[u8]) -> Result<T, ProgramError> {
+    if buf.is_empty() {
+        return Ok(T::default());
+    }
+    read_field(buf)
+}
+
 impl MovieCommentCounter {
     pub const DISCRIMINATOR: &'static str = "counter";
     pub const SIZE: usize = (4 + MovieCommentCounter::DISCRIMINATOR.len()) + 1 + 8;
 }
 
+impl MovieAggregate {
+    pub const DISCRIMINATOR: &'static str = "movie";
+    pub const SIZE: usize = (4 + MovieAggregate::DISCRIMINATOR.len()) + 1 + 8 + 8 + (5 * 8);
+
+    // Ratings are validated to be 1-5 before they get here
+    pub fn add_rating(&mut self, rating: u8) {
+        self.review_count += 1;
+        self.rating_sum += rating as u64;
+        self.histogram[rating as usize - 1] += 1;
+    }
+
+    pub fn remove_rating(&mut self, rating: u8) {
+        self.review_count = self.review_count.saturating_sub(1);
+        self.rating_sum = self.rating_sum.saturating_sub(rating as u64);
+        let bucket = &mut self.histogram[rating as usize - 1];
+        *bucket = bucket.saturating_sub(1);
+    }
+}
+
 impl IsInitialized for MovieAccountState {
     fn is_initialized(&self) -> bool {
         self.is_initialized
     }
 }
 
+impl IsInitialized for ReviewScore {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for VoteReceipt {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for ModeratorConfig {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for TokenConfig {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for RewardReceipt {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for TipStats {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
 impl IsInitialized for MovieCommentCounter {
     fn is_initialized(&self) -> bool {
         self.is_initialized
@@ -83,6 +302,100 @@ impl IsInitialized for MovieComment {
     }
 }
 
+impl IsInitialized for MovieAggregate {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
 impl Sealed for MovieCommentCounter{}
 
 impl Sealed for MovieAccountState {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(BorshSerialize)]
+    struct LegacyMovieComment {
+        discriminator: String,
+        is_initialized: bool,
+        review: Pubkey,
+        commenter: Pubkey,
+        comment: String,
+        count: u64,
+    }
+
+    #[test]
+    fn reads_legacy_comment_layout() {
+        let review = Pubkey::new_unique();
+        let commenter = Pubkey::new_unique();
+        let data = LegacyMovieComment {
+            discriminator: MovieComment::DISCRIMINATOR.to_string(),
+            is_initialized: true,
+            review,
+            commenter,
+            comment: "Great review".to_string(),
+            count: 3,
+        }
+        .try_to_vec()
+        .unwrap();
+
+        let comment = MovieComment::try_from_account_data(&data).unwrap();
+        assert_eq!(comment.review, review);
+        assert_eq!(comment.commenter, commenter);
+        assert_eq!(comment.comment, "Great review");
+        assert_eq!(comment.count, 3);
+        assert!(!comment.deleted);
+        assert_eq!(comment.parent, None);
+        assert!(!comment.hidden);
+        assert_eq!(comment.thread_start(), 3);
+    }
+
+    #[test]
+    fn reads_current_comment_layout() {
+        let parent = Pubkey::new_unique();
+        let comment = MovieComment {
+            discriminator: MovieComment::DISCRIMINATOR.to_string(),
+            is_initialized: true,
+            review: Pubkey::new_unique(),
+            commenter: Pubkey::new_unique(),
+            comment: "Agreed".to_string(),
+            count: 0,
+            deleted: true,
+            parent: Some(parent),
+            hidden: true,
+            thread: 7,
+        };
+        let data = comment.try_to_vec().unwrap();
+        assert_eq!(
+            data.len(),
+            MovieComment::get_account_size(comment.comment.clone())
+        );
+
+        let read = MovieComment::try_from_account_data(&data).unwrap();
+        assert!(read.deleted);
+        assert_eq!(read.parent, Some(parent));
+        assert!(read.hidden);
+        assert_eq!(read.thread_start(), 7);
+    }
+
+    #[test]
+    fn rejects_truncated_comment() {
+        let data = LegacyMovieComment {
+            discriminator: MovieComment::DISCRIMINATOR.to_string(),
+            is_initialized: true,
+            review: Pubkey::new_unique(),
+            commenter: Pubkey::new_unique(),
+            comment: "Great review".to_string(),
+            count: 3,
+        }
+        .try_to_vec()
+        .unwrap();
+
+        assert_eq!(
+            MovieComment::try_from_account_data(&data[..data.len() - 4]).err(),
+            Some(ProgramError::InvalidAccountData)
+        );
+    }
+}
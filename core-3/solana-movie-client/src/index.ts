@@ -1,6 +1,7 @@
 import * as web3 from '@solana/web3.js'
 import * as borsh from '@project-serum/borsh'
 import * as fs from 'fs'
+import * as crypto from 'crypto'
 import dotenv from 'dotenv'
 dotenv.config()
 
@@ -27,6 +28,9 @@ async function airdropSolIfNeeded(signer: web3.Keypair, connection: web3.Connect
   }
 }
 
+const TOKEN_PROGRAM_ID = new web3.PublicKey('TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA')
+const ASSOCIATED_TOKEN_PROGRAM_ID = new web3.PublicKey('ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL')
+
 const movieInstructionLayout = borsh.struct([
   borsh.u8('variant'),
   borsh.str('title'),
@@ -34,6 +38,130 @@ const movieInstructionLayout = borsh.struct([
   borsh.str('description')
 ])
 
+// Rust's char::is_whitespace set. JS \s differs: it includes U+FEFF and
+// leaves out U+0085, so it can't be used here
+const RUST_WHITESPACE = /[\t\n\v\f\r \u0085\u00a0\u1680\u2000-\u200a\u2028\u2029\u202f\u205f\u3000]+/
+
+// Must match normalize_title in the program's pda.rs
+function normalizeTitle(title: string): string {
+  return title
+    .split(RUST_WHITESPACE)
+    .filter(word => word.length > 0)
+    .join(' ')
+    .toLowerCase()
+}
+
+function titleSeed(title: string): Buffer {
+  return crypto.createHash('sha256').update(normalizeTitle(title), 'utf8').digest()
+}
+
+async function findReviewAddress(reviewer: web3.PublicKey, title: string, programId: web3.PublicKey) {
+  return web3.PublicKey.findProgramAddress(
+    [reviewer.toBuffer(), titleSeed(title)],
+    programId
+  )
+}
+
+// Reviews from before title hashing live at an address seeded with the raw
+// title, which only exists for titles of at most 32 bytes
+async function findLegacyReviewAddress(reviewer: web3.PublicKey, title: string, programId: web3.PublicKey) {
+  const titleBytes = Buffer.from(title, 'utf8')
+  if (titleBytes.length > 32) {
+    return null
+  }
+  return web3.PublicKey.findProgramAddress(
+    [reviewer.toBuffer(), titleBytes],
+    programId
+  )
+}
+
+// Looks a review up at its hashed address first, then at the legacy one.
+// Legacy reviews can be moved over with MigrateReview
+async function findExistingReview(
+  connection: web3.Connection,
+  reviewer: web3.PublicKey,
+  title: string,
+  programId: web3.PublicKey
+) {
+  const [pda] = await findReviewAddress(reviewer, title, programId)
+  if (await connection.getAccountInfo(pda)) {
+    return { address: pda, legacy: false }
+  }
+
+  const legacy = await findLegacyReviewAddress(reviewer, title, programId)
+  if (legacy && await connection.getAccountInfo(legacy[0])) {
+    return { address: legacy[0], legacy: true }
+  }
+
+  return null
+}
+
+async function findMovieAddress(title: string, programId: web3.PublicKey) {
+  return web3.PublicKey.findProgramAddress(
+    [Buffer.from('movie'), titleSeed(title)],
+    programId
+  )
+}
+
+// The program's reward mint and the accounts AddMovieReview and AddComment need
+// to pay out rewards, in the order the program reads them
+async function findRewardAccounts(author: web3.PublicKey, receipt: web3.PublicKey, programId: web3.PublicKey) {
+  const [tokenConfig] = await web3.PublicKey.findProgramAddress([Buffer.from('token_config')], programId)
+  const [tokenMint] = await web3.PublicKey.findProgramAddress([Buffer.from('token_mint')], programId)
+  const [mintAuth] = await web3.PublicKey.findProgramAddress([Buffer.from('token_auth')], programId)
+  const authorTokenAccount = await findAssociatedTokenAddress(author, tokenMint)
+
+  return {
+    tokenMint,
+    authorTokenAccount,
+    keys: [
+      { pubkey: tokenConfig, isSigner: false, isWritable: false },
+      { pubkey: tokenMint, isSigner: false, isWritable: true },
+      { pubkey: mintAuth, isSigner: false, isWritable: false },
+      { pubkey: authorTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: receipt, isSigner: false, isWritable: true }
+    ]
+  }
+}
+
+async function findReviewRewardAddress(reviewer: web3.PublicKey, title: string, programId: web3.PublicKey) {
+  return web3.PublicKey.findProgramAddress(
+    [Buffer.from('review_reward'), reviewer.toBuffer(), titleSeed(title)],
+    programId
+  )
+}
+
+async function findAssociatedTokenAddress(owner: web3.PublicKey, mint: web3.PublicKey) {
+  const [address] = await web3.PublicKey.findProgramAddress(
+    [owner.toBuffer(), TOKEN_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+    ASSOCIATED_TOKEN_PROGRAM_ID
+  )
+  return address
+}
+
+// Associated token program CreateIdempotent, does nothing if the account
+// already exists. Built by hand so the client doesn't need @solana/spl-token
+function createAssociatedTokenAccountInstruction(
+  payer: web3.PublicKey,
+  tokenAccount: web3.PublicKey,
+  owner: web3.PublicKey,
+  mint: web3.PublicKey
+) {
+  return new web3.TransactionInstruction({
+    programId: ASSOCIATED_TOKEN_PROGRAM_ID,
+    data: Buffer.from([1]),
+    keys: [
+      { pubkey: payer, isSigner: true, isWritable: true },
+      { pubkey: tokenAccount, isSigner: false, isWritable: true },
+      { pubkey: owner, isSigner: false, isWritable: false },
+      { pubkey: mint, isSigner: false, isWritable: false },
+      { pubkey: web3.SystemProgram.programId, isSigner: false, isWritable: false },
+      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false }
+    ]
+  })
+}
+
 async function sendTestMovieReview(signer: web3.Keypair, programId: web3.PublicKey, connection: web3.Connection) {
   let buffer = Buffer.alloc(1000)
   const movieTitle = `Braveheart ${Math.random() * 1000000}`
@@ -49,15 +177,33 @@ async function sendTestMovieReview(signer: web3.Keypair, programId: web3.PublicK
 
   buffer = buffer.slice(0, movieInstructionLayout.getSpan(buffer))
 
-  const [pda] = await web3.PublicKey.findProgramAddress(
-    [signer.publicKey.toBuffer(), Buffer.from(movieTitle)],
-    programId
-  )
+  const [pda] = await findReviewAddress(signer.publicKey, movieTitle, programId)
 
   console.log("PDA is:", pda.toBase58())
 
+  const [counter] = await web3.PublicKey.findProgramAddress(
+    [pda.toBuffer(), Buffer.from('comment')],
+    programId
+  )
+  const [movie] = await findMovieAddress(movieTitle, programId)
+  const [receipt] = await findReviewRewardAddress(signer.publicKey, movieTitle, programId)
+  const rewards = await findRewardAccounts(signer.publicKey, receipt, programId)
+
   const transaction = new web3.Transaction()
 
+  // The reward is minted to the reviewer's token account, which has to exist first.
+  // Until the reward mint is set up the review goes through without a reward
+  if (await connection.getAccountInfo(rewards.tokenMint)) {
+    transaction.add(
+      createAssociatedTokenAccountInstruction(
+        signer.publicKey,
+        rewards.authorTokenAccount,
+        signer.publicKey,
+        rewards.tokenMint
+      )
+    )
+  }
+
   const instruction = new web3.TransactionInstruction({
     programId: programId,
     data: buffer,
@@ -76,7 +222,18 @@ async function sendTestMovieReview(signer: web3.Keypair, programId: web3.PublicK
         pubkey: web3.SystemProgram.programId,
         isSigner: false,
         isWritable: false
-      }
+      },
+      {
+        pubkey: counter,
+        isSigner: false,
+        isWritable: true
+      },
+      {
+        pubkey: movie,
+        isSigner: false,
+        isWritable: true
+      },
+      ...rewards.keys
     ]
   })
 
@@ -33,6 +33,23 @@ const studentIntroLayout = borsh.struct([
   borsh.str('intro'),
 ])
 
+// Cohorts are created and opened by the instructor
+const COHORT = 1
+
+function u64ToBuffer(value: number, littleEndian: boolean): Buffer {
+  const buffer = Buffer.alloc(8)
+  const high = Math.floor(value / 2 ** 32)
+  const low = value % 2 ** 32
+  if (littleEndian) {
+    buffer.writeUInt32LE(low, 0)
+    buffer.writeUInt32LE(high, 4)
+  } else {
+    buffer.writeUInt32BE(high, 0)
+    buffer.writeUInt32BE(low, 4)
+  }
+  return buffer
+}
+
 async function sendTestStudentIntro(signer: web3.Keypair, programId: web3.PublicKey, connection: web3.Connection) {
   let buffer = Buffer.alloc(1000)
   const studentName = `Batman ${Math.random() * 1000000}`
@@ -46,9 +63,19 @@ async function sendTestStudentIntro(signer: web3.Keypair, programId: web3.Public
   )
 
   buffer = buffer.slice(0, studentIntroLayout.getSpan(buffer))
+  // The cohort id trails the payload as a borsh u64
+  buffer = Buffer.concat([buffer, u64ToBuffer(COHORT, true)])
 
   const [pda] = await web3.PublicKey.findProgramAddress(
-    [signer.publicKey.toBuffer()],
+    [signer.publicKey.toBuffer(), u64ToBuffer(COHORT, false)],
+    programId
+  )
+  const [counter] = await web3.PublicKey.findProgramAddress(
+    [pda.toBuffer(), Buffer.from('reply')],
+    programId
+  )
+  const [cohort] = await web3.PublicKey.findProgramAddress(
+    [Buffer.from('cohort'), u64ToBuffer(COHORT, false)],
     programId
   )
 
@@ -74,6 +101,16 @@ async function sendTestStudentIntro(signer: web3.Keypair, programId: web3.Public
         pubkey: web3.SystemProgram.programId,
         isSigner: false,
         isWritable: false
+      },
+      {
+        pubkey: counter,
+        isSigner: false,
+        isWritable: true
+      },
+      {
+        pubkey: cohort,
+        isSigner: false,
+        isWritable: false
       }
     ]
   })
@@ -14,6 +14,66 @@ pub enum StakeError {
 
     #[error("Invalid stake account")]
     InvalidStakeAccount,
+
+    #[error("Reward mint is not controlled by the program mint authority")]
+    InvalidRewardMint,
+
+    #[error("Reward token account is not the user's associated token account")]
+    InvalidRewardTokenAccount,
+
+    #[error("NFT account is not owned by the token program")]
+    NftNotOwnedByTokenProgram,
+
+    #[error("NFT token account is not owned by the user")]
+    NftTokenAccountOwnerMismatch,
+
+    #[error("NFT token account mint does not match the NFT mint")]
+    NftMintMismatch,
+
+    #[error("NFT token account must hold exactly one token")]
+    InvalidNftAmount,
+
+    #[error("NFT mint must have 0 decimals and a supply of 1")]
+    InvalidNftMint,
+
+    #[error("Invalid stake pool account")]
+    InvalidPool,
+
+    #[error("Stake pool start time must be before its end time")]
+    InvalidPoolWindow,
+
+    #[error("Stake pool has already ended")]
+    PoolEnded,
+
+    #[error("Invalid NFT metadata account")]
+    InvalidMetadata,
+
+    #[error("NFT is not part of the pool collection")]
+    CollectionMismatch,
+
+    #[error("NFT collection is not verified")]
+    CollectionNotVerified,
+
+    #[error("Lockup duration cannot be negative")]
+    InvalidLockupDuration,
+
+    #[error("Stake is still locked up")]
+    StakeLocked,
+
+    #[error("Stake account is still staking an NFT")]
+    StakeStillActive,
+
+    #[error("Batch accounts are missing or not grouped per NFT")]
+    InvalidBatchAccounts,
+
+    #[error("The same stake account appears twice in a batch")]
+    DuplicateStakeAccount,
+
+    #[error("Invalid pool stats account")]
+    InvalidPoolStats,
+
+    #[error("Invalid staker account")]
+    InvalidStakerInfo,
 }
 
 impl From<StakeError> for ProgramError {
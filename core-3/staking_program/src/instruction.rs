@@ -1,24 +1,78 @@
-use solana_program::program_error::ProgramError;
+use borsh::BorshDeserialize;
+use solana_program::{clock::UnixTimestamp, program_error::ProgramError};
 
 pub enum StakeInstruction {
     InitializeStakeAccount,
-    Stake,
+    Stake {
+        lockup_duration: Option<UnixTimestamp>,
+    },
     Redeem,
     Unstake,
+    InitializePool {
+        reward_rate: u64,
+        start_time: UnixTimestamp,
+        end_time: UnixTimestamp,
+    },
+    CloseStakeAccount,
+    StakeBatch {
+        lockup_duration: Option<UnixTimestamp>,
+    },
+    RedeemBatch,
+    UnstakeBatch,
 }
 
 impl StakeInstruction {
     pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
-        let (&variant, _rest) = input
+        let (&variant, rest) = input
             .split_first()
             .ok_or(ProgramError::InvalidInstructionData)?;
 
         Ok(match variant {
             0 => Self::InitializeStakeAccount,
-            1 => Self::Stake,
+            1 => Self::Stake {
+                lockup_duration: unpack_lockup_duration(rest)?,
+            },
             2 => Self::Redeem,
             3 => Self::Unstake,
+            4 => {
+                let payload = InitializePoolPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::InitializePool {
+                    reward_rate: payload.reward_rate,
+                    start_time: payload.start_time,
+                    end_time: payload.end_time,
+                }
+            }
+            5 => Self::CloseStakeAccount,
+            6 => Self::StakeBatch {
+                lockup_duration: unpack_lockup_duration(rest)?,
+            },
+            7 => Self::RedeemBatch,
+            8 => Self::UnstakeBatch,
             _ => return Err(ProgramError::InvalidInstructionData),
         })
     }
 }
+
+// The lockup is optional, a bare Stake instruction still works
+fn unpack_lockup_duration(rest: &[u8]) -> Result<Option<UnixTimestamp>, ProgramError> {
+    if rest.is_empty() {
+        return Ok(None);
+    }
+
+    let payload =
+        StakePayload::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?;
+    Ok(payload.lockup_duration)
+}
+
+#[derive(BorshDeserialize)]
+struct StakePayload {
+    lockup_duration: Option<UnixTimestamp>,
+}
+
+#[derive(BorshDeserialize)]
+struct InitializePoolPayload {
+    reward_rate: u64,
+    start_time: UnixTimestamp,
+    end_time: UnixTimestamp,
+}
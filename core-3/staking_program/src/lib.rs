@@ -1,5 +1,6 @@
 mod entrypoint;
 mod error;
 mod instruction;
+mod metaplex;
 mod processor;
 mod state;
@@ -0,0 +1,235 @@
+use borsh::BorshDeserialize;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+// Metaplex token-metadata program, we only need a couple of its instructions
+// so they're built by hand instead of pulling in the whole mpl crate
+solana_program::declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+pub const PREFIX: &str = "metadata";
+pub const EDITION: &str = "edition";
+
+// Key::MetadataV1, the first byte of every metadata account
+const METADATA_V1_KEY: u8 = 4;
+
+// Borsh enum discriminants of MetadataInstruction
+const FREEZE_DELEGATED_ACCOUNT: u8 = 26;
+const THAW_DELEGATED_ACCOUNT: u8 = 27;
+
+pub fn find_metadata_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PREFIX.as_bytes(), ID.as_ref(), mint.as_ref()], &ID)
+}
+
+pub fn find_master_edition_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            ID.as_ref(),
+            mint.as_ref(),
+            EDITION.as_bytes(),
+        ],
+        &ID,
+    )
+}
+
+pub fn freeze_delegated_account(
+    delegate: &Pubkey,
+    token_account: &Pubkey,
+    edition: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    delegated_account_instruction(
+        FREEZE_DELEGATED_ACCOUNT,
+        delegate,
+        token_account,
+        edition,
+        mint,
+    )
+}
+
+pub fn thaw_delegated_account(
+    delegate: &Pubkey,
+    token_account: &Pubkey,
+    edition: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    delegated_account_instruction(
+        THAW_DELEGATED_ACCOUNT,
+        delegate,
+        token_account,
+        edition,
+        mint,
+    )
+}
+
+fn delegated_account_instruction(
+    variant: u8,
+    delegate: &Pubkey,
+    token_account: &Pubkey,
+    edition: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: vec![
+            AccountMeta::new(*delegate, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(*edition, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: vec![variant],
+    }
+}
+
+// Leading fields of the token-metadata account layout, everything after
+// `collection` is left unread. Most fields are only here to keep the offsets right
+#[allow(dead_code)]
+#[derive(BorshDeserialize, Debug)]
+pub struct Metadata {
+    pub key: u8,
+    pub update_authority: Pubkey,
+    pub mint: Pubkey,
+    pub data: Data,
+    pub primary_sale_happened: bool,
+    pub is_mutable: bool,
+    pub edition_nonce: Option<u8>,
+    pub token_standard: Option<u8>,
+    pub collection: Option<Collection>,
+}
+
+#[allow(dead_code)]
+#[derive(BorshDeserialize, Debug)]
+pub struct Data {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub seller_fee_basis_points: u16,
+    pub creators: Option<Vec<Creator>>,
+}
+
+#[allow(dead_code)]
+#[derive(BorshDeserialize, Debug)]
+pub struct Creator {
+    pub address: Pubkey,
+    pub verified: bool,
+    pub share: u8,
+}
+
+#[derive(BorshDeserialize, Debug)]
+pub struct Collection {
+    pub verified: bool,
+    pub key: Pubkey,
+}
+
+impl Metadata {
+    pub fn from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        // deserialize() reads only the prefix it needs, unlike try_from_slice
+        let metadata =
+            Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)?;
+
+        if metadata.key != METADATA_V1_KEY {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_string(buf: &mut Vec<u8>, s: &str) {
+        buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        buf.extend_from_slice(s.as_bytes());
+    }
+
+    // Metadata as token-metadata writes it: null padded strings and the
+    // account zero filled past the fields we read
+    fn metadata_bytes(
+        key: u8,
+        update_authority: &Pubkey,
+        mint: &Pubkey,
+        collection: &Pubkey,
+    ) -> Vec<u8> {
+        let creator = Pubkey::new_unique();
+        let mut buf = vec![key];
+        buf.extend_from_slice(update_authority.as_ref());
+        buf.extend_from_slice(mint.as_ref());
+        push_string(&mut buf, "Staked NFT #1\0\0\0");
+        push_string(&mut buf, "STK\0\0");
+        push_string(&mut buf, "https://example.com/1.json");
+        buf.extend_from_slice(&500u16.to_le_bytes());
+        // creators: Some(vec![creator])
+        buf.push(1);
+        buf.extend_from_slice(&1u32.to_le_bytes());
+        buf.extend_from_slice(creator.as_ref());
+        buf.push(1);
+        buf.push(100);
+        // primary_sale_happened, is_mutable
+        buf.push(1);
+        buf.push(0);
+        // edition_nonce: Some(254), token_standard: Some(NonFungible)
+        buf.extend_from_slice(&[1, 254]);
+        buf.extend_from_slice(&[1, 0]);
+        // collection: Some(verified)
+        buf.push(1);
+        buf.push(1);
+        buf.extend_from_slice(collection.as_ref());
+        buf.resize(679, 0);
+        buf
+    }
+
+    #[test]
+    fn reads_metadata_layout() {
+        let update_authority = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let collection = Pubkey::new_unique();
+        let data = metadata_bytes(METADATA_V1_KEY, &update_authority, &mint, &collection);
+
+        let metadata = Metadata::from_account_data(&data).unwrap();
+        assert_eq!(metadata.update_authority, update_authority);
+        assert_eq!(metadata.mint, mint);
+        assert_eq!(metadata.data.name, "Staked NFT #1\0\0\0");
+        assert_eq!(metadata.data.symbol, "STK\0\0");
+        assert_eq!(metadata.data.uri, "https://example.com/1.json");
+        assert_eq!(metadata.data.seller_fee_basis_points, 500);
+        assert_eq!(metadata.data.creators.as_ref().unwrap()[0].share, 100);
+        assert!(metadata.primary_sale_happened);
+        assert!(!metadata.is_mutable);
+        assert_eq!(metadata.edition_nonce, Some(254));
+        assert_eq!(metadata.token_standard, Some(0));
+
+        let collection_field = metadata.collection.unwrap();
+        assert!(collection_field.verified);
+        assert_eq!(collection_field.key, collection);
+    }
+
+    #[test]
+    fn rejects_other_account_keys() {
+        let key = Pubkey::new_unique();
+        // Key::MasterEditionV2
+        let data = metadata_bytes(6, &key, &key, &key);
+
+        assert_eq!(
+            Metadata::from_account_data(&data).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let key = Pubkey::new_unique();
+        let data = metadata_bytes(METADATA_V1_KEY, &key, &key, &key);
+
+        assert_eq!(
+            Metadata::from_account_data(&data[..80]).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+        assert!(Metadata::from_account_data(&[]).is_err());
+    }
+}
@@ -1,20 +1,27 @@
 use crate::error::StakeError;
 use crate::instruction::StakeInstruction;
-use crate::state::{StakeState, UserStakeInfo};
+use crate::metaplex;
+use crate::state::{LockupTier, PoolStats, StakePool, StakeState, StakerInfo, UserStakeInfo};
 use borsh::BorshSerialize;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     borsh::try_from_slice_unchecked,
-    clock::Clock,
+    clock::{Clock, UnixTimestamp},
     entrypoint::ProgramResult,
     msg,
-    program::invoke_signed,
+    program::{invoke, invoke_signed, set_return_data},
     program_error::ProgramError,
-    program_pack::IsInitialized,
+    program_option::COption,
+    program_pack::{IsInitialized, Pack},
     pubkey::Pubkey,
-    system_instruction,
+    system_instruction, system_program,
     sysvar::{rent::Rent, Sysvar},
 };
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::{
+    instruction::{approve, mint_to, revoke},
+    state::{Account as TokenAccount, Mint},
+};
 
 pub fn process_instruction(
     program_id: &Pubkey,
@@ -27,12 +34,133 @@ pub fn process_instruction(
         StakeInstruction::InitializeStakeAccount => {
             process_initialize_stake_account(program_id, accounts)
         }
-        StakeInstruction::Stake => process_stake(program_id, accounts),
+        StakeInstruction::Stake { lockup_duration } => {
+            process_stake(program_id, accounts, lockup_duration)
+        }
         StakeInstruction::Redeem => process_redeem(program_id, accounts),
         StakeInstruction::Unstake => process_unstake(program_id, accounts),
+        StakeInstruction::InitializePool {
+            reward_rate,
+            start_time,
+            end_time,
+        } => process_initialize_pool(program_id, accounts, reward_rate, start_time, end_time),
+        StakeInstruction::CloseStakeAccount => process_close_stake_account(program_id, accounts),
+        StakeInstruction::StakeBatch { lockup_duration } => {
+            process_stake_batch(program_id, accounts, lockup_duration)
+        }
+        StakeInstruction::RedeemBatch => process_redeem_batch(program_id, accounts),
+        StakeInstruction::UnstakeBatch => process_unstake_batch(program_id, accounts),
     }
 }
 
+fn process_initialize_pool(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    reward_rate: u64,
+    start_time: UnixTimestamp,
+    end_time: UnixTimestamp,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let admin = next_account_info(account_info_iter)?;
+    let pool = next_account_info(account_info_iter)?;
+    let reward_mint = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let collection_mint = next_account_info(account_info_iter)?;
+    let pool_stats = next_account_info(account_info_iter)?;
+
+    if !admin.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (pool_pda, bump_seed) = Pubkey::find_program_address(
+        &[
+            StakePool::SEED,
+            admin.key.as_ref(),
+            reward_mint.key.as_ref(),
+        ],
+        program_id,
+    );
+
+    if pool_pda != *pool.key {
+        msg!("Invalid seeds for PDA");
+        return Err(StakeError::InvalidPda.into());
+    }
+
+    if *reward_mint.owner != spl_token::id() {
+        msg!("Reward mint is not owned by the token program");
+        return Err(StakeError::InvalidRewardMint.into());
+    }
+
+    if start_time >= end_time {
+        msg!("Pool must start before it ends");
+        return Err(StakeError::InvalidPoolWindow.into());
+    }
+
+    msg!("Creating pool account at {:?}", pool_pda);
+    create_pda_account(
+        program_id,
+        admin,
+        pool,
+        system_program,
+        StakePool::SIZE,
+        &[
+            StakePool::SEED,
+            admin.key.as_ref(),
+            reward_mint.key.as_ref(),
+            &[bump_seed],
+        ],
+    )?;
+
+    let mut pool_data = try_from_slice_unchecked::<StakePool>(&pool.data.borrow()).unwrap();
+
+    if pool_data.is_initialized() {
+        msg!("Account already initialized");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    pool_data.authority = *admin.key;
+    pool_data.reward_mint = *reward_mint.key;
+    pool_data.collection = *collection_mint.key;
+    pool_data.reward_rate = reward_rate;
+    pool_data.start_time = start_time;
+    pool_data.end_time = end_time;
+    pool_data.bump = bump_seed;
+    pool_data.is_initialized = true;
+
+    pool_data.serialize(&mut &mut pool.data.borrow_mut()[..])?;
+
+    msg!("Creating pool stats");
+    let (stats_pda, stats_bump) =
+        Pubkey::find_program_address(&[pool.key.as_ref(), PoolStats::SEED], program_id);
+    if stats_pda != *pool_stats.key {
+        msg!("Invalid seeds for PDA");
+        return Err(StakeError::InvalidPda.into());
+    }
+
+    create_pda_account(
+        program_id,
+        admin,
+        pool_stats,
+        system_program,
+        PoolStats::SIZE,
+        &[pool.key.as_ref(), PoolStats::SEED, &[stats_bump]],
+    )?;
+
+    let mut stats_data = try_from_slice_unchecked::<PoolStats>(&pool_stats.data.borrow()).unwrap();
+
+    if stats_data.is_initialized() {
+        msg!("Account already initialized");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    stats_data.pool = *pool.key;
+    stats_data.is_initialized = true;
+    stats_data.serialize(&mut &mut pool_stats.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
 fn process_initialize_stake_account(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
@@ -42,6 +170,9 @@ fn process_initialize_stake_account(
     let nft_token_account = next_account_info(account_info_iter)?;
     let stake_state = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
+    let nft_mint = next_account_info(account_info_iter)?;
+    let pool = next_account_info(account_info_iter)?;
+    let staker_info = next_account_info(account_info_iter)?;
 
     let (stake_state_pda, bump_seed) = Pubkey::find_program_address(
         &[user.key.as_ref(), nft_token_account.key.as_ref()],
@@ -54,24 +185,21 @@ fn process_initialize_stake_account(
         return Err(StakeError::InvalidPda.into());
     }
 
-    let rent = Rent::get()?;
-    let rent_lamports = rent.minimum_balance(UserStakeInfo::SIZE);
+    validate_nft_token_account(user, nft_token_account, nft_mint)?;
+    load_pool(program_id, pool)?;
 
     msg!("Creating state account at {:?}", stake_state_pda);
-    invoke_signed(
-        &system_instruction::create_account(
-            user.key,
-            stake_state.key,
-            rent_lamports,
-            UserStakeInfo::SIZE.try_into().unwrap(),
-            program_id,
-        ),
-        &[user.clone(), stake_state.clone(), system_program.clone()],
-        &[&[
+    create_pda_account(
+        program_id,
+        user,
+        stake_state,
+        system_program,
+        UserStakeInfo::SIZE,
+        &[
             user.key.as_ref(),
             nft_token_account.key.as_ref(),
             &[bump_seed],
-        ]],
+        ],
     )?;
 
     // Let's create account
@@ -86,108 +214,541 @@ fn process_initialize_stake_account(
     account_data.token_account = *nft_token_account.key;
     account_data.user_pubkey = *user.key;
     account_data.stake_state = StakeState::Unstaked;
+    account_data.pool = *pool.key;
+    account_data.bump = bump_seed;
     account_data.is_initialized = true;
 
     account_data.serialize(&mut &mut stake_state.data.borrow_mut()[..])?;
 
+    // The first stake account a wallet opens in a pool also opens its staker record
+    let (staker_pda, staker_bump) = Pubkey::find_program_address(
+        &[pool.key.as_ref(), user.key.as_ref(), StakerInfo::SEED],
+        program_id,
+    );
+    if staker_pda != *staker_info.key {
+        msg!("Invalid seeds for PDA");
+        return Err(StakeError::InvalidPda.into());
+    }
+
+    // Checked by owner rather than lamports, anyone can send lamports to the address
+    if staker_info.owner != program_id {
+        msg!("Creating staker account at {:?}", staker_pda);
+        create_pda_account(
+            program_id,
+            user,
+            staker_info,
+            system_program,
+            StakerInfo::SIZE,
+            &[
+                pool.key.as_ref(),
+                user.key.as_ref(),
+                StakerInfo::SEED,
+                &[staker_bump],
+            ],
+        )?;
+
+        let mut staker_data =
+            try_from_slice_unchecked::<StakerInfo>(&staker_info.data.borrow()).unwrap();
+        staker_data.pool = *pool.key;
+        staker_data.user_pubkey = *user.key;
+        staker_data.is_initialized = true;
+        staker_data.serialize(&mut &mut staker_info.data.borrow_mut()[..])?;
+    }
+
     Ok(())
 }
 
-fn process_stake(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+fn process_stake(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    lockup_duration: Option<UnixTimestamp>,
+) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
     let user = next_account_info(account_info_iter)?;
     let nft_token_account = next_account_info(account_info_iter)?;
     let stake_state = next_account_info(account_info_iter)?;
+    let nft_mint = next_account_info(account_info_iter)?;
+    let nft_edition = next_account_info(account_info_iter)?;
+    let program_authority = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let metadata_program = next_account_info(account_info_iter)?;
+    let pool = next_account_info(account_info_iter)?;
+    let nft_metadata = next_account_info(account_info_iter)?;
+    let pool_stats = next_account_info(account_info_iter)?;
+    let staker_info = next_account_info(account_info_iter)?;
 
-    let (stake_state_pda, _bump_seed) = Pubkey::find_program_address(
-        &[user.key.as_ref(), nft_token_account.key.as_ref()],
+    let ctx = StakeContext::new(program_id, user, pool)?;
+    let lock = LockContext::new(
         program_id,
-    );
+        program_authority,
+        token_program,
+        metadata_program,
+    )?;
+    let lockup = ctx.lockup(lockup_duration)?;
 
-    if stake_state_pda != *stake_state.key {
-        msg!("Invalid seeds for PDA");
-        return Err(StakeError::InvalidPda.into());
-    }
+    let nft = NftAccounts {
+        token_account: nft_token_account,
+        stake_state,
+        mint: nft_mint,
+        edition: nft_edition,
+    };
+    stake_nft(&ctx, &lock, &nft, nft_metadata, lockup)?;
+    record_stake_change(&ctx, pool_stats, staker_info, StakeChange::Staked(1))
+}
 
-    // Let's create account
-    let mut account_data =
-        try_from_slice_unchecked::<UserStakeInfo>(&stake_state.data.borrow()).unwrap();
+fn process_stake_batch(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    lockup_duration: Option<UnixTimestamp>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let user = next_account_info(account_info_iter)?;
+    let program_authority = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let metadata_program = next_account_info(account_info_iter)?;
+    let pool = next_account_info(account_info_iter)?;
+    let pool_stats = next_account_info(account_info_iter)?;
+    let staker_info = next_account_info(account_info_iter)?;
 
-    if !account_data.is_initialized() {
-        msg!("Account not initialized");
-        return Err(ProgramError::UninitializedAccount.into());
+    let ctx = StakeContext::new(program_id, user, pool)?;
+    let lock = LockContext::new(
+        program_id,
+        program_authority,
+        token_program,
+        metadata_program,
+    )?;
+    let lockup = ctx.lockup(lockup_duration)?;
+
+    // Remaining accounts: (token account, stake state, mint, edition, metadata) per NFT
+    let nft_accounts = batch_chunks(account_info_iter.as_slice(), 5)?;
+    for chunk in nft_accounts.chunks_exact(5) {
+        let nft = NftAccounts {
+            token_account: &chunk[0],
+            stake_state: &chunk[1],
+            mint: &chunk[2],
+            edition: &chunk[3],
+        };
+        stake_nft(&ctx, &lock, &nft, &chunk[4], lockup)?;
     }
 
-    let clock = Clock::get()?;
+    let staked = (nft_accounts.len() / 5) as u64;
+    msg!("Staked {} NFTs", staked);
+    record_stake_change(&ctx, pool_stats, staker_info, StakeChange::Staked(staked))
+}
 
-    account_data.token_account = *nft_token_account.key;
-    account_data.user_pubkey = *user.key;
-    account_data.stake_state = StakeState::Staked;
-    account_data.stake_start_time = clock.unix_timestamp;
-    account_data.last_stake_redeem = clock.unix_timestamp;
-    account_data.is_initialized = true;
+fn process_redeem(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let user = next_account_info(account_info_iter)?;
+    let nft_token_account = next_account_info(account_info_iter)?;
+    let stake_state = next_account_info(account_info_iter)?;
+    let stake_mint = next_account_info(account_info_iter)?;
+    let stake_authority = next_account_info(account_info_iter)?;
+    let user_stake_ata = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let pool = next_account_info(account_info_iter)?;
+    let pool_stats = next_account_info(account_info_iter)?;
 
-    account_data.serialize(&mut &mut stake_state.data.borrow_mut()[..])?;
-    Ok(())
+    let ctx = StakeContext::new(program_id, user, pool)?;
+    let reward_accounts = RewardAccounts {
+        stake_mint,
+        stake_authority,
+        user_stake_ata,
+        token_program,
+    };
+
+    let (account_data, redeem_amount) = redeem_nft(&ctx, nft_token_account, stake_state)?;
+    settle_rewards(
+        &ctx,
+        &reward_accounts,
+        pool_stats,
+        vec![(stake_state, account_data)],
+        redeem_amount,
+    )
 }
 
-fn process_redeem(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+fn process_redeem_batch(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let user = next_account_info(account_info_iter)?;
+    let stake_mint = next_account_info(account_info_iter)?;
+    let stake_authority = next_account_info(account_info_iter)?;
+    let user_stake_ata = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let pool = next_account_info(account_info_iter)?;
+    let pool_stats = next_account_info(account_info_iter)?;
+
+    let ctx = StakeContext::new(program_id, user, pool)?;
+    let reward_accounts = RewardAccounts {
+        stake_mint,
+        stake_authority,
+        user_stake_ata,
+        token_program,
+    };
+
+    // Remaining accounts: (token account, stake state) per NFT
+    let nft_accounts = batch_chunks(account_info_iter.as_slice(), 2)?;
+    let mut settled = Vec::with_capacity(nft_accounts.len() / 2);
+    let mut total: u64 = 0;
+    for chunk in nft_accounts.chunks_exact(2) {
+        let (account_data, redeem_amount) = redeem_nft(&ctx, &chunk[0], &chunk[1])?;
+        total = total
+            .checked_add(redeem_amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+        settled.push((&chunk[1], account_data));
+    }
+
+    settle_rewards(&ctx, &reward_accounts, pool_stats, settled, total)
+}
+
+fn process_unstake(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
     let user = next_account_info(account_info_iter)?;
     let nft_token_account = next_account_info(account_info_iter)?;
     let stake_state = next_account_info(account_info_iter)?;
+    let stake_mint = next_account_info(account_info_iter)?;
+    let stake_authority = next_account_info(account_info_iter)?;
+    let user_stake_ata = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let nft_mint = next_account_info(account_info_iter)?;
+    let nft_edition = next_account_info(account_info_iter)?;
+    let program_authority = next_account_info(account_info_iter)?;
+    let metadata_program = next_account_info(account_info_iter)?;
+    let pool = next_account_info(account_info_iter)?;
+    let pool_stats = next_account_info(account_info_iter)?;
+    let staker_info = next_account_info(account_info_iter)?;
 
-    let (stake_state_pda, _bump_seed) = Pubkey::find_program_address(
-        &[user.key.as_ref(), nft_token_account.key.as_ref()],
+    let ctx = StakeContext::new(program_id, user, pool)?;
+    let lock = LockContext::new(
         program_id,
-    );
+        program_authority,
+        token_program,
+        metadata_program,
+    )?;
+    let reward_accounts = RewardAccounts {
+        stake_mint,
+        stake_authority,
+        user_stake_ata,
+        token_program,
+    };
 
-    if stake_state_pda != *stake_state.key {
-        msg!("Invalid seeds for PDA");
-        return Err(StakeError::InvalidPda.into());
+    let nft = NftAccounts {
+        token_account: nft_token_account,
+        stake_state,
+        mint: nft_mint,
+        edition: nft_edition,
+    };
+    let (account_data, redeem_amount) = unstake_nft(&ctx, &lock, &nft)?;
+    settle_rewards(
+        &ctx,
+        &reward_accounts,
+        pool_stats,
+        vec![(stake_state, account_data)],
+        redeem_amount,
+    )?;
+    record_stake_change(&ctx, pool_stats, staker_info, StakeChange::Unstaked(1))
+}
+
+fn process_unstake_batch(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let user = next_account_info(account_info_iter)?;
+    let stake_mint = next_account_info(account_info_iter)?;
+    let stake_authority = next_account_info(account_info_iter)?;
+    let user_stake_ata = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let program_authority = next_account_info(account_info_iter)?;
+    let metadata_program = next_account_info(account_info_iter)?;
+    let pool = next_account_info(account_info_iter)?;
+    let pool_stats = next_account_info(account_info_iter)?;
+    let staker_info = next_account_info(account_info_iter)?;
+
+    let ctx = StakeContext::new(program_id, user, pool)?;
+    let lock = LockContext::new(
+        program_id,
+        program_authority,
+        token_program,
+        metadata_program,
+    )?;
+    let reward_accounts = RewardAccounts {
+        stake_mint,
+        stake_authority,
+        user_stake_ata,
+        token_program,
+    };
+
+    // Remaining accounts: (token account, stake state, mint, edition) per NFT
+    let nft_accounts = batch_chunks(account_info_iter.as_slice(), 4)?;
+    let mut settled = Vec::with_capacity(nft_accounts.len() / 4);
+    let mut total: u64 = 0;
+    for chunk in nft_accounts.chunks_exact(4) {
+        let nft = NftAccounts {
+            token_account: &chunk[0],
+            stake_state: &chunk[1],
+            mint: &chunk[2],
+            edition: &chunk[3],
+        };
+        let (account_data, redeem_amount) = unstake_nft(&ctx, &lock, &nft)?;
+        total = total
+            .checked_add(redeem_amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+        settled.push((&chunk[1], account_data));
     }
 
-    // For verification, we need to make sure it's the right signer
-    if !user.is_signer {
-        msg!("Missing required signature");
-        return Err(ProgramError::MissingRequiredSignature);
+    let unstaked = settled.len() as u64;
+    settle_rewards(&ctx, &reward_accounts, pool_stats, settled, total)?;
+    record_stake_change(
+        &ctx,
+        pool_stats,
+        staker_info,
+        StakeChange::Unstaked(unstaked),
+    )
+}
+
+// Checks the remaining accounts of a batch split evenly into groups of `group_len`
+// and that no stake state account (always second in a group) is repeated
+fn batch_chunks<'b, 'a>(
+    accounts: &'b [AccountInfo<'a>],
+    group_len: usize,
+) -> Result<&'b [AccountInfo<'a>], ProgramError> {
+    if accounts.is_empty() || !accounts.chunks_exact(group_len).remainder().is_empty() {
+        msg!("Expected a multiple of {} accounts per NFT", group_len);
+        return Err(StakeError::InvalidBatchAccounts.into());
     }
 
-    // Let's create account
-    let mut account_data =
-        try_from_slice_unchecked::<UserStakeInfo>(&stake_state.data.borrow()).unwrap();
+    let stake_states: Vec<&Pubkey> = accounts
+        .chunks_exact(group_len)
+        .map(|chunk| chunk[1].key)
+        .collect();
+    for (i, key) in stake_states.iter().enumerate() {
+        if stake_states[..i].contains(key) {
+            msg!("Stake account {:?} is listed twice", key);
+            return Err(StakeError::DuplicateStakeAccount.into());
+        }
+    }
 
-    if !account_data.is_initialized() {
-        msg!("Account not initialized");
-        return Err(ProgramError::UninitializedAccount.into());
+    Ok(accounts)
+}
+
+// Accounts and config shared by every NFT handled in one instruction
+struct StakeContext<'a, 'b> {
+    program_id: &'b Pubkey,
+    user: &'b AccountInfo<'a>,
+    pool: &'b AccountInfo<'a>,
+    pool_data: StakePool,
+    now: UnixTimestamp,
+}
+
+impl<'a, 'b> StakeContext<'a, 'b> {
+    fn new(
+        program_id: &'b Pubkey,
+        user: &'b AccountInfo<'a>,
+        pool: &'b AccountInfo<'a>,
+    ) -> Result<Self, ProgramError> {
+        // For verification, we need to make sure it's the right signer
+        if !user.is_signer {
+            msg!("Missing required signature");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let pool_data = load_pool(program_id, pool)?;
+        let clock = Clock::get()?;
+
+        Ok(Self {
+            program_id,
+            user,
+            pool,
+            pool_data,
+            now: clock.unix_timestamp,
+        })
+    }
+
+    // Unlock time and multiplier tier for a stake made now
+    fn lockup(
+        &self,
+        lockup_duration: Option<UnixTimestamp>,
+    ) -> Result<(UnixTimestamp, LockupTier), ProgramError> {
+        let lockup_duration = lockup_duration.unwrap_or(0);
+        if lockup_duration < 0 {
+            msg!("Lockup duration cannot be negative");
+            return Err(StakeError::InvalidLockupDuration.into());
+        }
+
+        let unlock_time = self
+            .now
+            .checked_add(lockup_duration)
+            .ok_or(StakeError::InvalidLockupDuration)?;
+        Ok((unlock_time, LockupTier::from_duration(lockup_duration)))
+    }
+
+    // Reads a stake account and checks it belongs to this user, token account and pool.
+    // The PDA is re-derived from the stored bump, which is much cheaper than
+    // find_program_address when handling a whole wallet in one instruction
+    fn load_stake_state(
+        &self,
+        nft_token_account: &AccountInfo,
+        stake_state: &AccountInfo,
+    ) -> Result<UserStakeInfo, ProgramError> {
+        if stake_state.owner != self.program_id {
+            msg!("Stake account is not owned by the program");
+            return Err(StakeError::InvalidStakeAccount.into());
+        }
+
+        let account_data =
+            try_from_slice_unchecked::<UserStakeInfo>(&stake_state.data.borrow()).unwrap();
+
+        if !account_data.is_initialized() {
+            msg!("Account not initialized");
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let stake_state_pda = Pubkey::create_program_address(
+            &[
+                self.user.key.as_ref(),
+                nft_token_account.key.as_ref(),
+                &[account_data.bump],
+            ],
+            self.program_id,
+        )
+        .map_err(|_| StakeError::InvalidPda)?;
+
+        if stake_state_pda != *stake_state.key {
+            msg!("Invalid seeds for PDA");
+            return Err(StakeError::InvalidPda.into());
+        }
+
+        if *self.user.key != account_data.user_pubkey {
+            msg!("Incorrect stake account for user");
+            return Err(StakeError::InvalidStakeAccount.into());
+        }
+
+        if *nft_token_account.key != account_data.token_account {
+            msg!("NFT Token account do not match");
+            return Err(StakeError::InvalidTokenAccount.into());
+        }
+
+        if account_data.pool == Pubkey::default() {
+            msg!("Stake account predates pools, close it and initialize a new one");
+            return Err(StakeError::InvalidPool.into());
+        }
+
+        if account_data.pool != *self.pool.key {
+            msg!("Stake account belongs to a different pool");
+            return Err(StakeError::InvalidPool.into());
+        }
+
+        Ok(account_data)
+    }
+}
+
+// The per-NFT accounts needed to freeze or thaw a staked NFT
+struct NftAccounts<'a, 'b> {
+    token_account: &'b AccountInfo<'a>,
+    stake_state: &'b AccountInfo<'a>,
+    mint: &'b AccountInfo<'a>,
+    edition: &'b AccountInfo<'a>,
+}
+
+fn stake_nft<'a>(
+    ctx: &StakeContext<'a, '_>,
+    lock: &LockContext<'a, '_>,
+    nft: &NftAccounts<'a, '_>,
+    nft_metadata: &AccountInfo,
+    (unlock_time, lockup_tier): (UnixTimestamp, LockupTier),
+) -> ProgramResult {
+    let mut account_data = ctx.load_stake_state(nft.token_account, nft.stake_state)?;
+
+    if account_data.stake_state == StakeState::Staked {
+        msg!("NFT is already staked");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    validate_nft_token_account(ctx.user, nft.token_account, nft.mint)?;
+
+    if ctx.now >= ctx.pool_data.end_time {
+        msg!("Pool has ended");
+        return Err(StakeError::PoolEnded.into());
     }
 
+    validate_collection(nft.mint, nft_metadata, &ctx.pool_data)?;
+
+    lock.freeze(ctx.user, nft)?;
+
+    msg!("Locking stake until {} ({:?})", unlock_time, lockup_tier);
+    account_data.stake_state = StakeState::Staked;
+    account_data.stake_start_time = ctx.now;
+    account_data.last_stake_redeem = ctx.now;
+    account_data.unlock_time = unlock_time;
+    account_data.lockup_tier = lockup_tier;
+
+    account_data.serialize(&mut &mut nft.stake_state.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+// Works out what a staked NFT is owed. The caller writes the returned state
+// back once the rewards have been minted
+fn redeem_nft(
+    ctx: &StakeContext,
+    nft_token_account: &AccountInfo,
+    stake_state: &AccountInfo,
+) -> Result<(UserStakeInfo, u64), ProgramError> {
+    let mut account_data = ctx.load_stake_state(nft_token_account, stake_state)?;
+
     if account_data.stake_state != StakeState::Staked {
         msg!("Stake account is not staking anything");
         return Err(ProgramError::InvalidArgument);
     }
 
-    if *user.key != account_data.user_pubkey {
-        msg!("Incorrect stake account for user");
-        return Err(StakeError::InvalidStakeAccount.into());
+    let redeem_amount = pending_rewards(&account_data, &ctx.pool_data, ctx.now)?;
+    msg!("Redeeming {} tokens", redeem_amount);
+
+    account_data.last_stake_redeem = ctx.now;
+    Ok((account_data, redeem_amount))
+}
+
+fn unstake_nft<'a>(
+    ctx: &StakeContext<'a, '_>,
+    lock: &LockContext<'a, '_>,
+    nft: &NftAccounts<'a, '_>,
+) -> Result<(UserStakeInfo, u64), ProgramError> {
+    let (mut account_data, redeem_amount) = redeem_nft(ctx, nft.token_account, nft.stake_state)?;
+
+    if ctx.now < account_data.unlock_time {
+        msg!("Stake is locked until {}", account_data.unlock_time);
+        return Err(StakeError::StakeLocked.into());
     }
 
-    if *nft_token_account.key != account_data.token_account {
-        msg!("NFT Token account do not match");
-        return Err(StakeError::InvalidTokenAccount.into());
+    lock.thaw(ctx.user, nft)?;
+
+    msg!("Setting stake state to unstaked");
+    account_data.stake_state = StakeState::Unstaked;
+    Ok((account_data, redeem_amount))
+}
+
+// Mints the total owed in a single CPI, then advances every redeem checkpoint
+fn settle_rewards<'a>(
+    ctx: &StakeContext<'a, '_>,
+    reward_accounts: &RewardAccounts<'a, '_>,
+    pool_stats: &AccountInfo,
+    settled: Vec<(&AccountInfo<'a>, UserStakeInfo)>,
+    total: u64,
+) -> ProgramResult {
+    mint_rewards(ctx, reward_accounts, total)?;
+
+    for (stake_state, account_data) in settled {
+        account_data.serialize(&mut &mut stake_state.data.borrow_mut()[..])?;
     }
 
-    let clock = Clock::get()?;
-    let unix_time = clock.unix_timestamp - account_data.last_stake_redeem;
-    let redeem_amount = unix_time;
-    msg!("Redeeming {} tokens", redeem_amount);
+    let mut stats_data = load_pool_stats(ctx, pool_stats)?;
+    stats_data.total_rewards_minted = stats_data
+        .total_rewards_minted
+        .checked_add(total)
+        .ok_or(ProgramError::InvalidArgument)?;
+    stats_data.serialize(&mut &mut pool_stats.data.borrow_mut()[..])?;
 
-    account_data.last_stake_redeem = clock.unix_timestamp;
-    account_data.serialize(&mut &mut stake_state.data.borrow_mut()[..])?;
+    msg!("Redeemed {} tokens in total", total);
+    set_return_data(&total.to_le_bytes());
     Ok(())
 }
 
-fn process_unstake(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+fn process_close_stake_account(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
     let user = next_account_info(account_info_iter)?;
     let nft_token_account = next_account_info(account_info_iter)?;
@@ -203,33 +764,542 @@ fn process_unstake(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResu
         return Err(StakeError::InvalidPda.into());
     }
 
-    // For verification, we need to make sure it's the right signer
+    if stake_state.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
     if !user.is_signer {
         msg!("Missing required signature");
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    // Let's create account
-    let mut account_data =
+    let account_data =
         try_from_slice_unchecked::<UserStakeInfo>(&stake_state.data.borrow()).unwrap();
 
     if !account_data.is_initialized() {
         msg!("Account not initialized");
-        return Err(ProgramError::UninitializedAccount.into());
+        return Err(ProgramError::UninitializedAccount);
     }
 
-    if account_data.stake_state != StakeState::Staked {
-        msg!("Stake account is not staking anything");
-        return Err(ProgramError::InvalidArgument);
+    if *user.key != account_data.user_pubkey {
+        msg!("Incorrect stake account for user");
+        return Err(StakeError::InvalidStakeAccount.into());
     }
 
-    let clock = Clock::get()?;
-    let unix_time = clock.unix_timestamp - account_data.last_stake_redeem;
-    let redeem_amount = unix_time;
-    msg!("Redeeming {} tokens", redeem_amount);
+    // Unstaking pays out everything still owed, so an unstaked account has no
+    // unclaimed rewards left. Accounts from before pools existed can't be
+    // unstaked, but staking back then never locked the NFT or accrued rewards
+    // so they can be closed in any state
+    let predates_pools = account_data.pool == Pubkey::default();
+    if account_data.stake_state != StakeState::Unstaked && !predates_pools {
+        msg!("Unstake before closing the stake account");
+        return Err(StakeError::StakeStillActive.into());
+    }
+
+    msg!("Closing stake account {:?}", stake_state.key);
+    let user_lamports = user.lamports();
+    **user.lamports.borrow_mut() = user_lamports
+        .checked_add(stake_state.lamports())
+        .ok_or(ProgramError::InvalidArgument)?;
+    **stake_state.lamports.borrow_mut() = 0;
+
+    // Wipe the data and hand the account back to the system program, so if
+    // anyone tops it back up later in the same transaction it can't be read
+    // as a live stake account again
+    stake_state.data.borrow_mut().fill(0);
+    stake_state.realloc(0, false)?;
+    stake_state.assign(&system_program::id());
+
+    Ok(())
+}
+
+enum StakeChange {
+    Staked(u64),
+    Unstaked(u64),
+}
+
+// Keeps the pool stats and the user's staker record in step as NFTs are
+// staked and unstaked
+fn record_stake_change(
+    ctx: &StakeContext,
+    pool_stats: &AccountInfo,
+    staker_info: &AccountInfo,
+    change: StakeChange,
+) -> ProgramResult {
+    let mut stats_data = load_pool_stats(ctx, pool_stats)?;
+    let mut staker_data = load_staker_info(ctx, staker_info)?;
+    let was_staking = staker_data.active_stakes > 0;
+
+    match change {
+        StakeChange::Staked(count) => {
+            staker_data.active_stakes = staker_data
+                .active_stakes
+                .checked_add(count)
+                .ok_or(ProgramError::InvalidArgument)?;
+            stats_data.staked_count = stats_data
+                .staked_count
+                .checked_add(count)
+                .ok_or(ProgramError::InvalidArgument)?;
+        }
+        StakeChange::Unstaked(count) => {
+            staker_data.active_stakes = staker_data
+                .active_stakes
+                .checked_sub(count)
+                .ok_or(ProgramError::InvalidArgument)?;
+            stats_data.staked_count = stats_data
+                .staked_count
+                .checked_sub(count)
+                .ok_or(ProgramError::InvalidArgument)?;
+        }
+    }
+
+    let is_staking = staker_data.active_stakes > 0;
+    if is_staking && !was_staking {
+        stats_data.unique_stakers += 1;
+    } else if was_staking && !is_staking {
+        stats_data.unique_stakers = stats_data.unique_stakers.saturating_sub(1);
+    }
+
+    msg!(
+        "Pool has {} NFTs staked by {} wallets",
+        stats_data.staked_count,
+        stats_data.unique_stakers
+    );
+    staker_data.serialize(&mut &mut staker_info.data.borrow_mut()[..])?;
+    stats_data.serialize(&mut &mut pool_stats.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+fn load_pool_stats(
+    ctx: &StakeContext,
+    pool_stats: &AccountInfo,
+) -> Result<PoolStats, ProgramError> {
+    let (stats_pda, _stats_bump) =
+        Pubkey::find_program_address(&[ctx.pool.key.as_ref(), PoolStats::SEED], ctx.program_id);
+    if stats_pda != *pool_stats.key || pool_stats.owner != ctx.program_id {
+        msg!("Invalid pool stats account");
+        return Err(StakeError::InvalidPoolStats.into());
+    }
+
+    let stats_data = try_from_slice_unchecked::<PoolStats>(&pool_stats.data.borrow())
+        .map_err(|_| StakeError::InvalidPoolStats)?;
+    if !stats_data.is_initialized() {
+        msg!("Pool stats not initialized");
+        return Err(StakeError::InvalidPoolStats.into());
+    }
+
+    Ok(stats_data)
+}
+
+fn load_staker_info(
+    ctx: &StakeContext,
+    staker_info: &AccountInfo,
+) -> Result<StakerInfo, ProgramError> {
+    let (staker_pda, _staker_bump) = Pubkey::find_program_address(
+        &[
+            ctx.pool.key.as_ref(),
+            ctx.user.key.as_ref(),
+            StakerInfo::SEED,
+        ],
+        ctx.program_id,
+    );
+    if staker_pda != *staker_info.key || staker_info.owner != ctx.program_id {
+        msg!("Invalid staker account");
+        return Err(StakeError::InvalidStakerInfo.into());
+    }
+
+    let staker_data = try_from_slice_unchecked::<StakerInfo>(&staker_info.data.borrow())
+        .map_err(|_| StakeError::InvalidStakerInfo)?;
+    if !staker_data.is_initialized() {
+        msg!("Staker account not initialized");
+        return Err(StakeError::InvalidStakerInfo.into());
+    }
+
+    Ok(staker_data)
+}
+
+// Creates a program owned PDA. An address that was already sent lamports can't
+// go through create_account, so it's topped up to rent and allocated instead
+fn create_pda_account<'a>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    size: usize,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    let rent_lamports = Rent::get()?.minimum_balance(size);
+
+    if account.lamports() == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                account.key,
+                rent_lamports,
+                size.try_into().unwrap(),
+                program_id,
+            ),
+            &[payer.clone(), account.clone(), system_program.clone()],
+            &[seeds],
+        );
+    }
+
+    let shortfall = rent_lamports.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, shortfall),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+
+    invoke_signed(
+        &system_instruction::allocate(account.key, size.try_into().unwrap()),
+        &[account.clone(), system_program.clone()],
+        &[seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, program_id),
+        &[account.clone(), system_program.clone()],
+        &[seeds],
+    )
+}
+
+// Makes sure the token account really holds the user's NFT
+fn validate_nft_token_account(
+    user: &AccountInfo,
+    nft_token_account: &AccountInfo,
+    nft_mint: &AccountInfo,
+) -> ProgramResult {
+    if *nft_token_account.owner != spl_token::id() || *nft_mint.owner != spl_token::id() {
+        msg!("NFT accounts are not owned by the token program");
+        return Err(StakeError::NftNotOwnedByTokenProgram.into());
+    }
+
+    let token_data = TokenAccount::unpack(&nft_token_account.data.borrow())?;
+    if token_data.owner != *user.key {
+        msg!("NFT token account is not owned by the user");
+        return Err(StakeError::NftTokenAccountOwnerMismatch.into());
+    }
+
+    if token_data.mint != *nft_mint.key {
+        msg!("NFT token account mint does not match");
+        return Err(StakeError::NftMintMismatch.into());
+    }
+
+    if token_data.amount != 1 {
+        msg!("NFT token account must hold exactly one token");
+        return Err(StakeError::InvalidNftAmount.into());
+    }
+
+    let mint_data = Mint::unpack(&nft_mint.data.borrow())?;
+    if mint_data.decimals != 0 || mint_data.supply != 1 {
+        msg!("Mint is not an NFT");
+        return Err(StakeError::InvalidNftMint.into());
+    }
+
+    Ok(())
+}
+
+// Only NFTs from the pool's verified Metaplex collection can be staked
+fn validate_collection(
+    nft_mint: &AccountInfo,
+    nft_metadata: &AccountInfo,
+    pool_data: &StakePool,
+) -> ProgramResult {
+    let (metadata_pda, _metadata_bump) = metaplex::find_metadata_address(nft_mint.key);
+    if metadata_pda != *nft_metadata.key {
+        msg!("Invalid metadata PDA for NFT mint");
+        return Err(StakeError::InvalidMetadata.into());
+    }
+
+    if *nft_metadata.owner != metaplex::id() {
+        msg!("Metadata is not owned by the token metadata program");
+        return Err(StakeError::InvalidMetadata.into());
+    }
+
+    let metadata = metaplex::Metadata::from_account_data(&nft_metadata.data.borrow())
+        .map_err(|_| StakeError::InvalidMetadata)?;
+
+    if metadata.mint != *nft_mint.key {
+        msg!("Metadata mint does not match the NFT mint");
+        return Err(StakeError::InvalidMetadata.into());
+    }
+
+    let collection = match metadata.collection {
+        Some(collection) if collection.key == pool_data.collection => collection,
+        _ => {
+            msg!("NFT is not part of the pool collection");
+            return Err(StakeError::CollectionMismatch.into());
+        }
+    };
+
+    if !collection.verified {
+        msg!("NFT collection is not verified");
+        return Err(StakeError::CollectionNotVerified.into());
+    }
+
+    Ok(())
+}
+
+// Program accounts shared by every freeze/thaw in one instruction
+struct LockContext<'a, 'b> {
+    program_authority: &'b AccountInfo<'a>,
+    token_program: &'b AccountInfo<'a>,
+    metadata_program: &'b AccountInfo<'a>,
+    authority_bump: u8,
+}
+
+impl<'a, 'b> LockContext<'a, 'b> {
+    fn new(
+        program_id: &Pubkey,
+        program_authority: &'b AccountInfo<'a>,
+        token_program: &'b AccountInfo<'a>,
+        metadata_program: &'b AccountInfo<'a>,
+    ) -> Result<Self, ProgramError> {
+        if *token_program.key != spl_token::id() {
+            msg!("Incorrect token program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if *metadata_program.key != metaplex::id() {
+            msg!("Incorrect metadata program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let (authority_pda, authority_bump) =
+            Pubkey::find_program_address(&[b"authority"], program_id);
+        if authority_pda != *program_authority.key {
+            msg!("Invalid program authority");
+            return Err(StakeError::InvalidPda.into());
+        }
+
+        Ok(Self {
+            program_authority,
+            token_program,
+            metadata_program,
+            authority_bump,
+        })
+    }
+
+    // Approves the program authority as delegate and freezes the NFT in the user's wallet
+    fn freeze(&self, user: &AccountInfo<'a>, nft: &NftAccounts<'a, '_>) -> ProgramResult {
+        Self::validate_edition(nft)?;
+
+        msg!("Approving program authority as delegate");
+        invoke(
+            &approve(
+                self.token_program.key,
+                nft.token_account.key,
+                self.program_authority.key,
+                user.key,
+                &[user.key],
+                1,
+            )?,
+            &[
+                nft.token_account.clone(),
+                self.program_authority.clone(),
+                user.clone(),
+                self.token_program.clone(),
+            ],
+        )?;
+
+        msg!("Freezing NFT token account");
+        invoke_signed(
+            &metaplex::freeze_delegated_account(
+                self.program_authority.key,
+                nft.token_account.key,
+                nft.edition.key,
+                nft.mint.key,
+            ),
+            &self.metadata_accounts(nft),
+            &[&[b"authority", &[self.authority_bump]]],
+        )
+    }
+
+    // Thaws the NFT and revokes the program authority's delegation
+    fn thaw(&self, user: &AccountInfo<'a>, nft: &NftAccounts<'a, '_>) -> ProgramResult {
+        Self::validate_edition(nft)?;
+
+        msg!("Thawing NFT token account");
+        invoke_signed(
+            &metaplex::thaw_delegated_account(
+                self.program_authority.key,
+                nft.token_account.key,
+                nft.edition.key,
+                nft.mint.key,
+            ),
+            &self.metadata_accounts(nft),
+            &[&[b"authority", &[self.authority_bump]]],
+        )?;
+
+        msg!("Revoking program authority as delegate");
+        invoke(
+            &revoke(
+                self.token_program.key,
+                nft.token_account.key,
+                user.key,
+                &[user.key],
+            )?,
+            &[
+                nft.token_account.clone(),
+                user.clone(),
+                self.token_program.clone(),
+            ],
+        )
+    }
+
+    fn validate_edition(nft: &NftAccounts) -> ProgramResult {
+        let (edition_pda, _edition_bump) = metaplex::find_master_edition_address(nft.mint.key);
+        if edition_pda != *nft.edition.key {
+            msg!("Invalid master edition for NFT mint");
+            return Err(StakeError::InvalidPda.into());
+        }
+        Ok(())
+    }
+
+    fn metadata_accounts(&self, nft: &NftAccounts<'a, '_>) -> [AccountInfo<'a>; 6] {
+        [
+            self.program_authority.clone(),
+            nft.token_account.clone(),
+            nft.edition.clone(),
+            nft.mint.clone(),
+            self.token_program.clone(),
+            self.metadata_program.clone(),
+        ]
+    }
+}
+
+// Reads the pool config and checks it really is one of our pool PDAs
+fn load_pool(program_id: &Pubkey, pool: &AccountInfo) -> Result<StakePool, ProgramError> {
+    if pool.owner != program_id {
+        msg!("Pool is not owned by the program");
+        return Err(StakeError::InvalidPool.into());
+    }
+
+    let pool_data = try_from_slice_unchecked::<StakePool>(&pool.data.borrow())
+        .map_err(|_| StakeError::InvalidPool)?;
+
+    if !pool_data.is_initialized() {
+        msg!("Pool not initialized");
+        return Err(StakeError::InvalidPool.into());
+    }
+
+    let pool_pda = Pubkey::create_program_address(
+        &[
+            StakePool::SEED,
+            pool_data.authority.as_ref(),
+            pool_data.reward_mint.as_ref(),
+            &[pool_data.bump],
+        ],
+        program_id,
+    )
+    .map_err(|_| StakeError::InvalidPool)?;
+
+    if pool_pda != *pool.key {
+        msg!("Invalid seeds for pool PDA");
+        return Err(StakeError::InvalidPool.into());
+    }
+
+    Ok(pool_data)
+}
+
+fn pending_rewards(
+    account_data: &UserStakeInfo,
+    pool_data: &StakePool,
+    now: UnixTimestamp,
+) -> Result<u64, ProgramError> {
+    pool_data
+        .reward_with_lockup(
+            account_data.last_stake_redeem,
+            now,
+            account_data.unlock_time,
+            account_data.lockup_tier,
+        )
+        .ok_or(ProgramError::InvalidArgument)
+}
+
+// Accounts needed to mint staking rewards to a user
+struct RewardAccounts<'a, 'b> {
+    stake_mint: &'b AccountInfo<'a>,
+    stake_authority: &'b AccountInfo<'a>,
+    user_stake_ata: &'b AccountInfo<'a>,
+    token_program: &'b AccountInfo<'a>,
+}
+
+fn mint_rewards<'a>(
+    ctx: &StakeContext<'a, '_>,
+    reward_accounts: &RewardAccounts<'a, '_>,
+    amount: u64,
+) -> ProgramResult {
+    let StakeContext {
+        program_id,
+        user,
+        pool,
+        ref pool_data,
+        ..
+    } = *ctx;
+    let RewardAccounts {
+        stake_mint,
+        stake_authority,
+        user_stake_ata,
+        token_program,
+    } = *reward_accounts;
+
+    if *token_program.key != spl_token::id() {
+        msg!("Incorrect token program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // Each pool has its own mint authority so one pool can't mint another pool's rewards
+    let (stake_auth_pda, auth_bump) =
+        Pubkey::find_program_address(&[b"mint", pool.key.as_ref()], program_id);
+    if stake_auth_pda != *stake_authority.key {
+        msg!("Invalid mint authority");
+        return Err(StakeError::InvalidPda.into());
+    }
+
+    if *stake_mint.key != pool_data.reward_mint {
+        msg!("Reward mint does not match the pool");
+        return Err(StakeError::InvalidRewardMint.into());
+    }
+
+    // The reward mint must be an spl-token mint whose authority is our PDA
+    if *stake_mint.owner != spl_token::id() {
+        msg!("Reward mint is not owned by the token program");
+        return Err(StakeError::InvalidRewardMint.into());
+    }
+    let mint_data = Mint::unpack(&stake_mint.data.borrow())?;
+    if mint_data.mint_authority != COption::Some(stake_auth_pda) {
+        msg!("Reward mint authority is not the program PDA");
+        return Err(StakeError::InvalidRewardMint.into());
+    }
+
+    if get_associated_token_address(user.key, stake_mint.key) != *user_stake_ata.key {
+        msg!("Reward token account is not the user's ATA");
+        return Err(StakeError::InvalidRewardTokenAccount.into());
+    }
+
+    if amount == 0 {
+        return Ok(());
+    }
+
+    invoke_signed(
+        &mint_to(
+            token_program.key,
+            stake_mint.key,
+            user_stake_ata.key,
+            stake_authority.key,
+            &[stake_authority.key],
+            amount,
+        )?,
+        &[
+            stake_mint.clone(),
+            user_stake_ata.clone(),
+            stake_authority.clone(),
+            token_program.clone(),
+        ],
+        &[&[b"mint", pool.key.as_ref(), &[auth_bump]]],
+    )?;
 
-    msg!("Setting stake state to unstaked");
-    account_data.stake_state = StakeState::Unstaked;
-    account_data.serialize(&mut &mut stake_state.data.borrow_mut()[..])?;
     Ok(())
 }
@@ -13,9 +13,13 @@ pub struct UserStakeInfo {
     pub last_stake_redeem: UnixTimestamp,
     pub user_pubkey: Pubkey,
     pub stake_state: StakeState,
+    pub pool: Pubkey,
+    pub unlock_time: UnixTimestamp,
+    pub lockup_tier: LockupTier,
+    pub bump: u8,
 }
 impl UserStakeInfo {
-    /**
+    /*
         Here's how we determine the size of the data. In your UserStakeInfo in struct in state.rs, we have the following data.
 
         pub is_initialized: bool,                 // 1 bit
@@ -24,8 +28,12 @@ impl UserStakeInfo {
         pub last_stake_redeem: UnixTimestamp,     // 64 bits
         pub user_pubkey: Pubkey,                  // 32 bits
         pub stake_state: StakeState,              // 1 bit
-    **/
-    pub const SIZE: usize = 1 + 32 + 64 + 64 + 32 + 1;
+        pub pool: Pubkey,                         // 32 bytes
+        pub unlock_time: UnixTimestamp,           // 8 bytes
+        pub lockup_tier: LockupTier,              // 1 byte
+        pub bump: u8,                             // 1 byte
+    */
+    pub const SIZE: usize = 1 + 32 + 64 + 64 + 32 + 1 + 32 + 8 + 1 + 1;
 }
 
 impl Sealed for UserStakeInfo {}
@@ -35,8 +43,260 @@ impl IsInitialized for UserStakeInfo {
     }
 }
 
+// Admin-created config shared by every stake in a pool
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct StakePool {
+    pub is_initialized: bool,
+    pub authority: Pubkey,
+    pub reward_mint: Pubkey,
+    pub collection: Pubkey,
+    pub reward_rate: u64,
+    pub start_time: UnixTimestamp,
+    pub end_time: UnixTimestamp,
+    pub bump: u8,
+}
+impl StakePool {
+    pub const SEED: &'static [u8] = b"pool";
+    pub const SIZE: usize = 1 + 32 + 32 + 32 + 8 + 8 + 8 + 1;
+
+    // Rewards earned between two timestamps, only counting time inside the pool window
+    pub fn reward_for(&self, from: UnixTimestamp, to: UnixTimestamp) -> Option<u64> {
+        let start = from.max(self.start_time);
+        let end = to.min(self.end_time);
+        if end <= start {
+            return Some(0);
+        }
+        let seconds = u64::try_from(end - start).ok()?;
+        seconds.checked_mul(self.reward_rate)
+    }
+
+    // Like reward_for, with the lockup multiplier applied only to time before
+    // `unlock_time`. Once the NFT is free to leave it earns the base rate
+    pub fn reward_with_lockup(
+        &self,
+        from: UnixTimestamp,
+        to: UnixTimestamp,
+        unlock_time: UnixTimestamp,
+        tier: LockupTier,
+    ) -> Option<u64> {
+        let split = unlock_time.max(from).min(to);
+        let locked = self.reward_for(from, split)?;
+        let unlocked = self.reward_for(split, to)?;
+
+        let boosted =
+            u128::from(locked) * u128::from(tier.multiplier_bps()) / 10_000 + u128::from(unlocked);
+        u64::try_from(boosted).ok()
+    }
+}
+
+impl Sealed for StakePool {}
+impl IsInitialized for StakePool {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+// Pool-wide totals, so dashboards don't have to scan every UserStakeInfo
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct PoolStats {
+    pub is_initialized: bool,
+    pub pool: Pubkey,
+    pub staked_count: u64,
+    pub unique_stakers: u64,
+    pub total_rewards_minted: u64,
+}
+impl PoolStats {
+    pub const SEED: &'static [u8] = b"stats";
+    pub const SIZE: usize = 1 + 32 + 8 + 8 + 8;
+}
+
+impl Sealed for PoolStats {}
+impl IsInitialized for PoolStats {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+// Per-wallet record of how many NFTs a user has staked in a pool, used to
+// count unique stakers
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct StakerInfo {
+    pub is_initialized: bool,
+    pub pool: Pubkey,
+    pub user_pubkey: Pubkey,
+    pub active_stakes: u64,
+}
+impl StakerInfo {
+    pub const SEED: &'static [u8] = b"staker";
+    pub const SIZE: usize = 1 + 32 + 32 + 8;
+}
+
+impl Sealed for StakerInfo {}
+impl IsInitialized for StakerInfo {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
 pub enum StakeState {
     Staked,
     Unstaked,
 }
+
+// Longer lockups earn a bigger share of the pool reward rate
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Clone, Copy)]
+pub enum LockupTier {
+    None,
+    Week,
+    Month,
+    Quarter,
+}
+
+impl LockupTier {
+    const DAY: UnixTimestamp = 24 * 60 * 60;
+
+    // Picks the highest tier the lockup duration qualifies for
+    pub fn from_duration(duration: UnixTimestamp) -> Self {
+        [Self::Quarter, Self::Month, Self::Week]
+            .into_iter()
+            .find(|tier| duration >= tier.min_duration())
+            .unwrap_or(Self::None)
+    }
+
+    pub fn min_duration(&self) -> UnixTimestamp {
+        match self {
+            Self::None => 0,
+            Self::Week => 7 * Self::DAY,
+            Self::Month => 30 * Self::DAY,
+            Self::Quarter => 90 * Self::DAY,
+        }
+    }
+
+    // Reward multiplier in basis points, 10_000 is 1x
+    pub fn multiplier_bps(&self) -> u64 {
+        match self {
+            Self::None => 10_000,
+            Self::Week => 12_500,
+            Self::Month => 15_000,
+            Self::Quarter => 20_000,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: UnixTimestamp = LockupTier::DAY;
+    const HOUR: UnixTimestamp = 60 * 60;
+
+    fn pool(reward_rate: u64, start_time: UnixTimestamp, end_time: UnixTimestamp) -> StakePool {
+        StakePool {
+            is_initialized: true,
+            authority: Pubkey::new_unique(),
+            reward_mint: Pubkey::new_unique(),
+            collection: Pubkey::new_unique(),
+            reward_rate,
+            start_time,
+            end_time,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn lockup_tier_boundaries() {
+        assert_eq!(LockupTier::from_duration(0), LockupTier::None);
+        assert_eq!(LockupTier::from_duration(-DAY), LockupTier::None);
+        assert_eq!(
+            LockupTier::from_duration(6 * DAY + 23 * HOUR),
+            LockupTier::None
+        );
+        assert_eq!(LockupTier::from_duration(7 * DAY - 1), LockupTier::None);
+        assert_eq!(LockupTier::from_duration(7 * DAY), LockupTier::Week);
+        assert_eq!(LockupTier::from_duration(30 * DAY - 1), LockupTier::Week);
+        assert_eq!(LockupTier::from_duration(30 * DAY), LockupTier::Month);
+        assert_eq!(LockupTier::from_duration(90 * DAY - 1), LockupTier::Month);
+        assert_eq!(LockupTier::from_duration(90 * DAY), LockupTier::Quarter);
+        assert_eq!(LockupTier::from_duration(365 * DAY), LockupTier::Quarter);
+    }
+
+    #[test]
+    fn lockup_tier_round_trips_min_duration() {
+        for tier in [
+            LockupTier::None,
+            LockupTier::Week,
+            LockupTier::Month,
+            LockupTier::Quarter,
+        ] {
+            assert_eq!(LockupTier::from_duration(tier.min_duration()), tier);
+        }
+    }
+
+    #[test]
+    fn reward_for_inside_window() {
+        let pool = pool(3, 1_000, 2_000);
+        assert_eq!(pool.reward_for(1_100, 1_200), Some(300));
+    }
+
+    #[test]
+    fn reward_for_clamps_to_window() {
+        let pool = pool(3, 1_000, 2_000);
+        // staked before the pool opened
+        assert_eq!(pool.reward_for(500, 1_200), Some(600));
+        // claimed after the pool closed
+        assert_eq!(pool.reward_for(1_900, 2_500), Some(300));
+        // spans the whole window
+        assert_eq!(pool.reward_for(0, 5_000), Some(3_000));
+    }
+
+    #[test]
+    fn reward_for_outside_window_is_zero() {
+        let pool = pool(3, 1_000, 2_000);
+        assert_eq!(pool.reward_for(100, 900), Some(0));
+        assert_eq!(pool.reward_for(2_100, 3_000), Some(0));
+        assert_eq!(pool.reward_for(1_500, 1_500), Some(0));
+        // clock went backwards
+        assert_eq!(pool.reward_for(1_600, 1_500), Some(0));
+    }
+
+    #[test]
+    fn lockup_multiplier_stops_at_unlock() {
+        let pool = pool(10, 0, 1_000_000);
+        // Quarter is 2x, 100s locked then 50s unlocked
+        assert_eq!(
+            pool.reward_with_lockup(1_000, 1_150, 1_100, LockupTier::Quarter),
+            Some(100 * 10 * 2 + 50 * 10)
+        );
+        // still locked for the whole window
+        assert_eq!(
+            pool.reward_with_lockup(1_000, 1_100, 2_000, LockupTier::Month),
+            Some(100 * 10 * 3 / 2)
+        );
+        // unlocked before the window started
+        assert_eq!(
+            pool.reward_with_lockup(1_000, 1_100, 500, LockupTier::Quarter),
+            Some(100 * 10)
+        );
+    }
+
+    #[test]
+    fn lockup_split_respects_pool_window() {
+        let pool = pool(10, 1_000, 2_000);
+        // locked part starts before the pool opens, unlocked part runs past its end
+        assert_eq!(
+            pool.reward_with_lockup(500, 2_500, 1_500, LockupTier::Week),
+            Some(500 * 10 * 5 / 4 + 500 * 10)
+        );
+        assert_eq!(
+            pool.reward_with_lockup(1_600, 1_500, 1_550, LockupTier::Week),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn reward_for_overflow_is_none() {
+        let pool = pool(u64::MAX, 0, UnixTimestamp::MAX);
+        assert_eq!(pool.reward_for(0, 2), None);
+    }
+}
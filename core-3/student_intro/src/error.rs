@@ -1,7 +1,7 @@
 use solana_program::program_error::ProgramError;
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Error, PartialEq)]
 pub enum IntroError {
     // Error 0
     #[error("Account not initialized yet")]
@@ -22,6 +22,42 @@ pub enum IntroError {
     // Error 4
     #[error("Intro is too large")]
     InvalidIntroLength,
+
+    // Error 5
+    #[error("Cohort is not open for intros")]
+    CohortClosed,
+
+    // Error 6
+    #[error("Signer is not the instructor")]
+    InvalidInstructor,
+
+    // Error 7
+    #[error("Signer is not the student")]
+    InvalidStudent,
+
+    // Error 8
+    #[error("Signer is not allowed to change this reply")]
+    InvalidReplier,
+
+    // Error 9
+    #[error("Name is empty")]
+    EmptyName,
+
+    // Error 10
+    #[error("Intro is empty")]
+    EmptyIntro,
+
+    // Error 11
+    #[error("Text contains control or invisible characters")]
+    ControlCharacter,
+
+    // Error 12
+    #[error("Text contains bidirectional control characters")]
+    BidiOverride,
+
+    // Error 13
+    #[error("Signer is not the upgrade authority")]
+    InvalidUpgradeAuthority,
 }
 
 impl From<IntroError> for ProgramError {
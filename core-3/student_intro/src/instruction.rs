@@ -1,10 +1,36 @@
 use borsh::BorshDeserialize;
-use solana_program::program_error::ProgramError;
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
 
 pub enum StudentIntroInstruction {
-    AddStudentIntro { name: String, msg: String },
-    UpdateStudentIntro { name: String, msg: String },
-    ReplyToIntro { reply: String },
+    AddStudentIntro {
+        name: String,
+        msg: String,
+        cohort: u64,
+    },
+    UpdateStudentIntro {
+        name: String,
+        msg: String,
+        cohort: u64,
+    },
+    ReplyToIntro {
+        reply: String,
+    },
+    SetInstructor {
+        instructor: Pubkey,
+    },
+    CreateCohort {
+        cohort: u64,
+    },
+    SetCohortOpen {
+        is_open: bool,
+    },
+    DeleteStudentIntro,
+    UpdateReply {
+        reply: String,
+    },
+    DeleteReply,
+    EndorseIntro,
+    RevokeEndorsement,
 }
 
 impl StudentIntroInstruction {
@@ -19,6 +45,7 @@ impl StudentIntroInstruction {
                 Self::AddStudentIntro {
                     name: payload.name,
                     msg: payload.msg,
+                    cohort: payload.cohort,
                 }
             }
             1 => {
@@ -26,6 +53,7 @@ impl StudentIntroInstruction {
                 Self::UpdateStudentIntro {
                     name: payload.name,
                     msg: payload.msg,
+                    cohort: payload.cohort,
                 }
             }
             2 => {
@@ -34,6 +62,34 @@ impl StudentIntroInstruction {
                     reply: payload.reply,
                 }
             }
+            3 => {
+                let payload = InstructorPayload::try_from_slice(rest).unwrap();
+                Self::SetInstructor {
+                    instructor: payload.instructor,
+                }
+            }
+            4 => {
+                let payload = CohortPayload::try_from_slice(rest).unwrap();
+                Self::CreateCohort {
+                    cohort: payload.cohort,
+                }
+            }
+            5 => {
+                let payload = CohortOpenPayload::try_from_slice(rest).unwrap();
+                Self::SetCohortOpen {
+                    is_open: payload.is_open,
+                }
+            }
+            6 => Self::DeleteStudentIntro,
+            7 => {
+                let payload = ReplyPayload::try_from_slice(rest).unwrap();
+                Self::UpdateReply {
+                    reply: payload.reply,
+                }
+            }
+            8 => Self::DeleteReply,
+            9 => Self::EndorseIntro,
+            10 => Self::RevokeEndorsement,
             _ => return Err(ProgramError::InvalidInstructionData),
         })
     }
@@ -43,9 +99,25 @@ impl StudentIntroInstruction {
 struct StudentIntroPayload {
     name: String,
     msg: String,
+    cohort: u64,
 }
 
 #[derive(BorshDeserialize)]
 struct ReplyPayload {
     reply: String,
 }
+
+#[derive(BorshDeserialize)]
+struct InstructorPayload {
+    instructor: Pubkey,
+}
+
+#[derive(BorshDeserialize)]
+struct CohortPayload {
+    cohort: u64,
+}
+
+#[derive(BorshDeserialize)]
+struct CohortOpenPayload {
+    is_open: bool,
+}
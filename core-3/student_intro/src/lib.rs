@@ -1,5 +1,7 @@
 mod entrypoint;
 mod error;
 mod instruction;
+pub mod pda;
 mod processor;
 mod state;
+mod validation;
@@ -0,0 +1,56 @@
+use solana_program::pubkey::Pubkey;
+use std::ops::Range;
+
+pub const CONFIG_SEED: &[u8] = b"config";
+pub const COHORT_SEED: &[u8] = b"cohort";
+
+// Intros are scoped to a cohort so the same wallet can post one per cohort
+pub fn find_intro_address(program_id: &Pubkey, student: &Pubkey, cohort: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[student.as_ref(), &cohort.to_be_bytes()], program_id)
+}
+
+// Intros created before cohorts were seeded with the student key alone
+pub fn find_legacy_intro_address(program_id: &Pubkey, student: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[student.as_ref()], program_id)
+}
+
+// Derived from the intro address, so the counter follows the intro's cohort
+pub fn find_reply_counter_address(program_id: &Pubkey, intro: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[intro.as_ref(), "reply".as_ref()], program_id)
+}
+
+pub fn find_cohort_address(program_id: &Pubkey, cohort: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[COHORT_SEED, &cohort.to_be_bytes()], program_id)
+}
+
+pub fn find_config_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONFIG_SEED], program_id)
+}
+
+// Numbered replies under an intro. Entry `i` is seeded with the intro key and `i`
+// as big endian bytes, the index the counter handed out and the one stored in `IntroReply::count`
+pub struct Seq<'a> {
+    program_id: &'a Pubkey,
+    parent: &'a Pubkey,
+}
+
+impl<'a> Seq<'a> {
+    pub fn new(program_id: &'a Pubkey, parent: &'a Pubkey) -> Self {
+        Self { program_id, parent }
+    }
+
+    pub fn find_address(&self, index: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[self.parent.as_ref(), &index.to_be_bytes()],
+            self.program_id,
+        )
+    }
+
+    // Addresses for `indexes` oldest first, `.rev()` pages back from the newest
+    pub fn addresses(
+        &self,
+        indexes: Range<u64>,
+    ) -> impl DoubleEndedIterator<Item = (u64, Pubkey)> + '_ {
+        indexes.map(move |index| (index, self.find_address(index).0))
+    }
+}
@@ -1,18 +1,22 @@
 use crate::instruction::StudentIntroInstruction;
-use crate::state::{IntroReply, StudentIntroAccountState};
+use crate::pda;
+use crate::state::{Cohort, IntroReply, ProgramConfig, StudentIntroAccountState};
+use crate::validation;
 use crate::{error::IntroError, state::IntroReplyCounter};
 use borsh::BorshSerialize;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     borsh::try_from_slice_unchecked,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
     entrypoint::ProgramResult,
     msg,
-    program::invoke_signed,
+    program::{invoke, invoke_signed},
     program_error::ProgramError,
     program_pack::IsInitialized,
+    program_utils::limited_deserialize,
     pubkey::Pubkey,
-    system_instruction,
-    sysvar::{rent::Rent, Sysvar},
+    system_instruction, system_program,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
 };
 
 use std::convert::TryInto;
@@ -22,6 +26,7 @@ pub fn update_student_intro(
     accounts: &[AccountInfo],
     name: String,
     msg: String,
+    cohort: u64,
 ) -> ProgramResult {
     msg!("Updating student intro");
     msg!("student name: {}", name);
@@ -45,11 +50,12 @@ pub fn update_student_intro(
         try_from_slice_unchecked::<StudentIntroAccountState>(&pda_account.data.borrow()).unwrap();
     msg!("borrowed account data");
 
-    // Derive PDA and check that it matches client
-    let (pda, _bump_seed) = Pubkey::find_program_address(&[initializer.key.as_ref()], program_id);
+    // Derive PDA and check that it matches client, intros from before cohorts are accepted too
+    let (pda, _bump_seed) = pda::find_intro_address(program_id, initializer.key, cohort);
+    let (legacy_pda, _legacy_bump) = pda::find_legacy_intro_address(program_id, initializer.key);
 
     // PDA check
-    if pda != *pda_account.key {
+    if pda != *pda_account.key && legacy_pda != *pda_account.key {
         msg!("Invalid seeds for PDA");
         return Err(IntroError::InvalidPDA.into());
     }
@@ -60,10 +66,10 @@ pub fn update_student_intro(
     }
 
     // data validation
-    if msg.len() > 50 {
-        msg!("Intro cannot be longer than 50 characters");
-        return Err(IntroError::InvalidIntroLength.into());
-    }
+    let msg = validation::normalize_msg(&msg).map_err(|err| {
+        msg!("Invalid intro: {}", err);
+        err
+    })?;
 
     // calculate account size required
     let account_len = 1 + (4 + msg.len());
@@ -73,8 +79,19 @@ pub fn update_student_intro(
         return Err(IntroError::InvalidDataLength.into());
     }
 
-    // Update student intro
+    // Update student intro, an endorsement covers the text it was given for so
+    // the instructor has to endorse the edited intro again
     account_data.msg = msg;
+    if let Some(instructor) = account_data.endorsed_by {
+        msg!(
+            "EVENT intro_endorsed intro={} student={} endorsed=false instructor={}",
+            pda_account.key,
+            account_data.student_key,
+            instructor
+        );
+    }
+    account_data.endorsed_by = None;
+    account_data.endorsed_at = None;
     account_data.serialize(&mut &mut pda_account.data.borrow_mut()[..])?;
     Ok(())
 }
@@ -84,6 +101,7 @@ pub fn add_student_intro(
     accounts: &[AccountInfo],
     name: String,
     msg: String,
+    cohort: u64,
 ) -> ProgramResult {
     msg!("Adding student intro");
     msg!("Student name:{}", name);
@@ -97,6 +115,7 @@ pub fn add_student_intro(
     let pda_account = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
     let pda_counter = next_account_info(account_info_iter)?;
+    let pda_cohort = next_account_info(account_info_iter)?;
 
     // signer check
     if !initializer.is_signer {
@@ -104,7 +123,13 @@ pub fn add_student_intro(
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    let (pda, bump_seed) = Pubkey::find_program_address(&[initializer.key.as_ref()], program_id);
+    let cohort_data = load_cohort(program_id, pda_cohort)?;
+    if cohort_data.cohort != cohort || !cohort_data.is_open {
+        msg!("Cohort {} is not open", cohort);
+        return Err(IntroError::CohortClosed.into());
+    }
+
+    let (pda, bump_seed) = pda::find_intro_address(program_id, initializer.key, cohort);
 
     // PDA check
     if pda != *pda_account.key {
@@ -113,15 +138,14 @@ pub fn add_student_intro(
     }
 
     // data validation
-    if name.len() > 15 {
-        msg!("Name cannot be longer than 15 characters");
-        return Err(IntroError::InvalidNameLength.into());
-    }
-
-    if msg.len() > 50 {
-        msg!("Intro cannot be longer than 50 characters");
-        return Err(IntroError::InvalidIntroLength.into());
-    }
+    let name = validation::normalize_name(&name).map_err(|err| {
+        msg!("Invalid name: {}", err);
+        err
+    })?;
+    let msg = validation::normalize_msg(&msg).map_err(|err| {
+        msg!("Invalid intro: {}", err);
+        err
+    })?;
 
     // calculate account size required
     let account_len: usize = 1000;
@@ -131,24 +155,17 @@ pub fn add_student_intro(
         return Err(IntroError::InvalidDataLength.into());
     }
 
-    // Calculate rent required
-    let rent = Rent::get()?;
-    let rent_lamports = rent.minimum_balance(account_len);
-
-    invoke_signed(
-        &system_instruction::create_account(
-            initializer.key,
-            pda_account.key,
-            rent_lamports,
-            account_len.try_into().unwrap(),
-            program_id,
-        ),
+    allocate_pda_account(
+        program_id,
+        initializer,
+        pda_account,
+        system_program,
+        account_len,
         &[
-            initializer.clone(),
-            pda_account.clone(),
-            system_program.clone(),
+            initializer.key.as_ref(),
+            &cohort.to_be_bytes(),
+            &[bump_seed],
         ],
-        &[&[initializer.key.as_ref(), &[bump_seed]]],
     )?;
     msg!("PDA created: {}", pda);
 
@@ -161,63 +178,78 @@ pub fn add_student_intro(
     account_data.student_key = *initializer.key;
     account_data.name = name;
     account_data.msg = msg;
+    account_data.cohort = cohort;
+    account_data.endorsed_by = None;
+    account_data.endorsed_at = None;
     account_data.is_initialized = true;
 
-    msg!("Creating reply counter");
-    let rent = Rent::get()?;
-    let counter_rent_lamports = rent.minimum_balance(IntroReplyCounter::SIZE);
+    let counter_data = load_or_create_reply_counter(
+        program_id,
+        initializer,
+        pda_account,
+        pda_counter,
+        system_program,
+    )?;
+    account_data.first_reply = counter_data.counter;
+
+    msg!("serializing account");
+    account_data.serialize(&mut &mut pda_account.data.borrow_mut()[..])?;
+    msg!("state account serialized");
+    Ok(())
+}
+
+// Deleting an intro leaves its reply counter behind, an intro added at the same
+// address carries on numbering from there so new replies don't land on the old
+// ones' addresses
+fn load_or_create_reply_counter<'a>(
+    program_id: &Pubkey,
+    initializer: &AccountInfo<'a>,
+    pda_account: &AccountInfo<'a>,
+    pda_counter: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+) -> Result<IntroReplyCounter, ProgramError> {
     // Deriving the address and validating that the correct seeds were passed in
-    let (counter, counter_bump) =
-        Pubkey::find_program_address(&[pda.as_ref(), "reply".as_ref()], program_id);
+    let (counter, counter_bump) = pda::find_reply_counter_address(program_id, pda_account.key);
     if counter != *pda_counter.key {
         msg!("Invalid seeds for PDA");
         return Err(ProgramError::InvalidArgument);
     }
 
-    // Creating the reply counter account
-    invoke_signed(
-        &system_instruction::create_account(
-            initializer.key,                             // Rent payer
-            pda_counter.key,       // Address who we're creating the account for
-            counter_rent_lamports, // Amount of rent to put into the account
-            IntroReplyCounter::SIZE.try_into().unwrap(), // Size of the account
-            program_id,
-        ),
-        &[
-            // List of accounts that will be read from/written to
-            initializer.clone(),
-            pda_counter.clone(),
-            system_program.clone(),
-        ],
+    if pda_counter.owner == program_id {
+        let counter_data =
+            try_from_slice_unchecked::<IntroReplyCounter>(&pda_counter.data.borrow())?;
+        if !counter_data.is_initialized() {
+            msg!("Reply counter is not initialized");
+            return Err(IntroError::UninitializedAccount.into());
+        }
+        msg!("Reusing reply counter at {}", counter_data.counter);
+        return Ok(counter_data);
+    }
+
+    msg!("Creating reply counter");
+    allocate_pda_account(
+        program_id,
+        initializer,
+        pda_counter,
+        system_program,
+        IntroReplyCounter::SIZE,
         // Seeds for the PDA
         // PDA account
         // The string "reply"
-        &[&[pda.as_ref(), "reply".as_ref(), &[counter_bump]]],
+        &[pda_account.key.as_ref(), "reply".as_ref(), &[counter_bump]],
     )?;
     msg!("reply counter created");
 
-    // Deserialize the newly created counter account
-    let mut counter_data =
-        try_from_slice_unchecked::<IntroReplyCounter>(&pda_counter.data.borrow()).unwrap();
-
-    msg!("checking if counter account is already initialized");
-    if counter_data.is_initialized() {
-        msg!("Account already initialized");
-        return Err(ProgramError::AccountAlreadyInitialized);
-    }
-
-    counter_data.discriminator = IntroReplyCounter::DISCRIMINATOR.to_string();
-    counter_data.counter = 0;
-    counter_data.is_initialized = true;
+    let counter_data = IntroReplyCounter {
+        discriminator: IntroReplyCounter::DISCRIMINATOR.to_string(),
+        is_initialized: true,
+        counter: 0,
+    };
     msg!("reply count: {}", counter_data.counter);
     counter_data.serialize(&mut &mut pda_counter.data.borrow_mut()[..])?;
 
     msg!("reply counter initialized");
-
-    msg!("serializing account");
-    account_data.serialize(&mut &mut pda_account.data.borrow_mut()[..])?;
-    msg!("state account serialized");
-    Ok(())
+    Ok(counter_data)
 }
 
 pub fn reply_to_intro(
@@ -236,41 +268,43 @@ pub fn reply_to_intro(
     let pda_reply = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
 
+    // A deleted intro's counter outlives it, so the intro itself has to be checked
+    if pda_intro.owner != program_id || pda_counter.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let (counter, _counter_bump) = pda::find_reply_counter_address(program_id, pda_intro.key);
+    if counter != *pda_counter.key {
+        msg!("Invalid seeds for PDA");
+        return Err(IntroError::InvalidPDA.into());
+    }
+
     let mut counter_data =
         try_from_slice_unchecked::<IntroReplyCounter>(&pda_counter.data.borrow()).unwrap();
 
     let account_len = IntroReply::get_account_size(reply.clone());
 
-    let rent = Rent::get()?;
-    let rent_lamports = rent.minimum_balance(account_len);
-
-    let (pda, bump_seed) = Pubkey::find_program_address(
-        &[
-            pda_intro.key.as_ref(),
-            counter_data.counter.to_be_bytes().as_ref(),
-        ],
-        program_id,
-    );
+    let (pda, bump_seed) =
+        pda::Seq::new(program_id, pda_intro.key).find_address(counter_data.counter);
 
     if pda != *pda_reply.key {
         msg!("Invalid seeds for PDA");
         return Err(IntroError::InvalidPDA.into());
     }
 
-    invoke_signed(
-        &system_instruction::create_account(
-            replier.key,
-            pda_reply.key,
-            rent_lamports,
-            account_len.try_into().unwrap(),
-            program_id,
-        ),
-        &[replier.clone(), pda_reply.clone(), system_program.clone()],
-        &[&[
+    // A funded next address would otherwise stop the sequence, and every
+    // reply after it, from ever being written
+    allocate_pda_account(
+        program_id,
+        replier,
+        pda_reply,
+        system_program,
+        account_len,
+        &[
             pda_intro.key.as_ref(),
             counter_data.counter.to_be_bytes().as_ref(),
             &[bump_seed],
-        ]],
+        ],
     )?;
 
     msg!("Created Reply Account");
@@ -287,6 +321,7 @@ pub fn reply_to_intro(
     reply_data.intro = *pda_intro.key;
     reply_data.replier = *replier.key;
     reply_data.reply = reply;
+    reply_data.count = counter_data.counter;
     reply_data.is_initialized = true;
     reply_data.serialize(&mut &mut pda_reply.data.borrow_mut()[..])?;
 
@@ -297,6 +332,521 @@ pub fn reply_to_intro(
     Ok(())
 }
 
+pub fn delete_student_intro(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    msg!("Deleting student intro");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let student = next_account_info(account_info_iter)?;
+    let pda_account = next_account_info(account_info_iter)?;
+    let pda_counter = next_account_info(account_info_iter)?;
+
+    if pda_account.owner != program_id || pda_counter.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    if !student.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let account_data =
+        try_from_slice_unchecked::<StudentIntroAccountState>(&pda_account.data.borrow()).unwrap();
+
+    if !account_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(IntroError::UninitializedAccount.into());
+    }
+
+    if account_data.student_key != *student.key {
+        msg!("Only the student can delete their intro");
+        return Err(IntroError::InvalidStudent.into());
+    }
+
+    let (pda, _bump_seed) = pda::find_intro_address(program_id, student.key, account_data.cohort);
+    let (legacy_pda, _legacy_bump) = pda::find_legacy_intro_address(program_id, student.key);
+    if pda != *pda_account.key && legacy_pda != *pda_account.key {
+        msg!("Invalid seeds for PDA");
+        return Err(IntroError::InvalidPDA.into());
+    }
+
+    let (counter, _counter_bump) = pda::find_reply_counter_address(program_id, pda_account.key);
+    if counter != *pda_counter.key {
+        msg!("Invalid seeds for PDA");
+        return Err(IntroError::InvalidPDA.into());
+    }
+
+    let counter_data =
+        try_from_slice_unchecked::<IntroReplyCounter>(&pda_counter.data.borrow()).unwrap();
+
+    // The counter is kept so an intro added at the same address numbers its
+    // replies after these ones
+    close_account(pda_account, student)?;
+
+    // Reply accounts are left in place, indexers can purge replies first_reply..replies of this intro
+    msg!(
+        "EVENT intro_deleted intro={} student={} replies={} first_reply={}",
+        pda_account.key,
+        student.key,
+        counter_data.counter,
+        account_data.first_reply
+    );
+
+    Ok(())
+}
+
+pub fn update_reply(program_id: &Pubkey, accounts: &[AccountInfo], reply: String) -> ProgramResult {
+    msg!("Updating reply");
+    msg!("Reply: {}", reply);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let replier = next_account_info(account_info_iter)?;
+    let pda_reply = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !replier.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mut reply_data = load_reply(program_id, pda_reply)?;
+    if reply_data.replier != *replier.key {
+        msg!("Only the replier can edit a reply");
+        return Err(IntroError::InvalidReplier.into());
+    }
+
+    reply_data.reply = reply;
+    let new_len = IntroReply::get_account_size(reply_data.reply.clone());
+    resize_account(pda_reply, replier, system_program, new_len)?;
+
+    reply_data.serialize(&mut &mut pda_reply.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+// Either the replier or the owner of the intro replied to can delete a reply,
+// the rent always goes back to the replier who paid it
+pub fn delete_reply(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    msg!("Deleting reply");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let signer = next_account_info(account_info_iter)?;
+    let pda_reply = next_account_info(account_info_iter)?;
+    let replier = next_account_info(account_info_iter)?;
+    let pda_intro = next_account_info(account_info_iter)?;
+
+    if !signer.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let reply_data = load_reply(program_id, pda_reply)?;
+    if reply_data.replier != *replier.key {
+        msg!("Rent must be refunded to the replier");
+        return Err(IntroError::InvalidReplier.into());
+    }
+
+    if reply_data.replier != *signer.key {
+        if pda_intro.owner != program_id || reply_data.intro != *pda_intro.key {
+            msg!("Intro does not match the reply");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let intro_data =
+            try_from_slice_unchecked::<StudentIntroAccountState>(&pda_intro.data.borrow()).unwrap();
+        if !intro_data.is_initialized() || intro_data.student_key != *signer.key {
+            msg!("Only the replier or the intro owner can delete a reply");
+            return Err(IntroError::InvalidReplier.into());
+        }
+    }
+
+    close_account(pda_reply, replier)?;
+    msg!("Reply {} deleted", pda_reply.key);
+
+    Ok(())
+}
+
+fn load_reply(program_id: &Pubkey, pda_reply: &AccountInfo) -> Result<IntroReply, ProgramError> {
+    if pda_reply.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let reply_data = try_from_slice_unchecked::<IntroReply>(&pda_reply.data.borrow()).unwrap();
+
+    if reply_data.discriminator != IntroReply::DISCRIMINATOR {
+        msg!("Account is not a reply");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !reply_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(IntroError::UninitializedAccount.into());
+    }
+
+    Ok(reply_data)
+}
+
+// Reallocs a program account to `new_len`, with the payer covering any extra
+// rent or getting the difference back when the account shrinks
+fn resize_account<'a>(
+    account: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    new_len: usize,
+) -> ProgramResult {
+    let rent = Rent::get()?;
+    let new_minimum_balance = rent.minimum_balance(new_len);
+    let current_lamports = account.lamports();
+
+    if new_minimum_balance > current_lamports {
+        msg!(
+            "Topping up rent by {}",
+            new_minimum_balance - current_lamports
+        );
+        invoke(
+            &system_instruction::transfer(
+                payer.key,
+                account.key,
+                new_minimum_balance - current_lamports,
+            ),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    } else if current_lamports > new_minimum_balance {
+        let refund = current_lamports - new_minimum_balance;
+        msg!("Refunding {} of rent", refund);
+        **account.lamports.borrow_mut() = new_minimum_balance;
+        **payer.lamports.borrow_mut() = payer
+            .lamports()
+            .checked_add(refund)
+            .ok_or(ProgramError::InvalidArgument)?;
+    }
+
+    account.realloc(new_len, false)
+}
+
+// Refunds the rent of a program account and wipes it, handing it back to the
+// system program so it can't be revived as a program account
+fn close_account(account: &AccountInfo, destination: &AccountInfo) -> ProgramResult {
+    let destination_lamports = destination.lamports();
+    **destination.lamports.borrow_mut() = destination_lamports
+        .checked_add(account.lamports())
+        .ok_or(ProgramError::InvalidArgument)?;
+    **account.lamports.borrow_mut() = 0;
+
+    account.data.borrow_mut().fill(0);
+    account.realloc(0, false)?;
+    account.assign(&system_program::id());
+    Ok(())
+}
+
+// The first call creates the config and names the instructor, after that only
+// the current instructor can hand the role to someone else
+pub fn set_instructor(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instructor: Pubkey,
+) -> ProgramResult {
+    msg!("Setting instructor");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let signer = next_account_info(account_info_iter)?;
+    let pda_config = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !signer.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (config, config_bump) = pda::find_config_address(program_id);
+    if config != *pda_config.key {
+        msg!("Invalid seeds for PDA");
+        return Err(IntroError::InvalidPDA.into());
+    }
+
+    // The first instructor is named by the upgrade authority, whose ProgramData
+    // account follows, after that only the current instructor can hand over
+    if pda_config.data_is_empty() {
+        let program_data = next_account_info(account_info_iter)?;
+        check_upgrade_authority(program_id, program_data, signer)?;
+
+        allocate_pda_account(
+            program_id,
+            signer,
+            pda_config,
+            system_program,
+            ProgramConfig::SIZE,
+            &[pda::CONFIG_SEED, &[config_bump]],
+        )?;
+    } else {
+        load_instructor_config(program_id, signer, pda_config)?;
+    }
+
+    let config_data = ProgramConfig {
+        discriminator: ProgramConfig::DISCRIMINATOR.to_string(),
+        is_initialized: true,
+        instructor,
+    };
+    config_data.serialize(&mut &mut pda_config.data.borrow_mut()[..])?;
+    msg!("Instructor: {}", instructor);
+
+    Ok(())
+}
+
+// One time setup is reserved for the upgrade authority recorded in the
+// program's ProgramData account, so it can't be claimed by whoever calls first
+fn check_upgrade_authority(
+    program_id: &Pubkey,
+    program_data: &AccountInfo,
+    authority: &AccountInfo,
+) -> ProgramResult {
+    if !authority.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (program_data_address, _bump) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    if program_data_address != *program_data.key
+        || *program_data.owner != bpf_loader_upgradeable::id()
+    {
+        msg!("Invalid program data account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let state = limited_deserialize::<UpgradeableLoaderState>(
+        &program_data.data.borrow(),
+        UpgradeableLoaderState::size_of_programdata_metadata() as u64,
+    )
+    .map_err(|_| ProgramError::InvalidAccountData)?;
+
+    match state {
+        UpgradeableLoaderState::ProgramData {
+            upgrade_authority_address: Some(upgrade_authority),
+            ..
+        } if upgrade_authority == *authority.key => Ok(()),
+        _ => {
+            msg!("Signer is not the upgrade authority");
+            Err(IntroError::InvalidUpgradeAuthority.into())
+        }
+    }
+}
+
+// Creates a PDA owned by the program. Anyone can send lamports to an address
+// before it exists, which makes create_account fail, so an address that already
+// holds lamports is topped up to rent and allocated and assigned instead
+fn allocate_pda_account<'a>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    size: usize,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    let rent_lamports = Rent::get()?.minimum_balance(size);
+
+    if account.lamports() == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                account.key,
+                rent_lamports,
+                size.try_into().unwrap(),
+                program_id,
+            ),
+            &[payer.clone(), account.clone(), system_program.clone()],
+            &[seeds],
+        );
+    }
+
+    let shortfall = rent_lamports.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, shortfall),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+
+    invoke_signed(
+        &system_instruction::allocate(account.key, size.try_into().unwrap()),
+        &[account.clone(), system_program.clone()],
+        &[seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, program_id),
+        &[account.clone(), system_program.clone()],
+        &[seeds],
+    )
+}
+
+pub fn create_cohort(program_id: &Pubkey, accounts: &[AccountInfo], cohort: u64) -> ProgramResult {
+    msg!("Creating cohort {}", cohort);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let instructor = next_account_info(account_info_iter)?;
+    let pda_config = next_account_info(account_info_iter)?;
+    let pda_cohort = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    load_instructor_config(program_id, instructor, pda_config)?;
+
+    let (pda, bump_seed) = pda::find_cohort_address(program_id, cohort);
+    if pda != *pda_cohort.key {
+        msg!("Invalid seeds for PDA");
+        return Err(IntroError::InvalidPDA.into());
+    }
+
+    allocate_pda_account(
+        program_id,
+        instructor,
+        pda_cohort,
+        system_program,
+        Cohort::SIZE,
+        &[pda::COHORT_SEED, &cohort.to_be_bytes(), &[bump_seed]],
+    )?;
+
+    // New cohorts start out open
+    let cohort_data = Cohort {
+        discriminator: Cohort::DISCRIMINATOR.to_string(),
+        is_initialized: true,
+        cohort,
+        instructor: *instructor.key,
+        is_open: true,
+    };
+    cohort_data.serialize(&mut &mut pda_cohort.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+pub fn set_cohort_open(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    is_open: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let instructor = next_account_info(account_info_iter)?;
+    let pda_config = next_account_info(account_info_iter)?;
+    let pda_cohort = next_account_info(account_info_iter)?;
+
+    load_instructor_config(program_id, instructor, pda_config)?;
+
+    let mut cohort_data = load_cohort(program_id, pda_cohort)?;
+    cohort_data.is_open = is_open;
+    cohort_data.serialize(&mut &mut pda_cohort.data.borrow_mut()[..])?;
+    msg!("Cohort {} open: {}", cohort_data.cohort, is_open);
+
+    Ok(())
+}
+
+pub fn set_endorsement(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    endorse: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let instructor = next_account_info(account_info_iter)?;
+    let pda_config = next_account_info(account_info_iter)?;
+    let pda_intro = next_account_info(account_info_iter)?;
+
+    load_instructor_config(program_id, instructor, pda_config)?;
+
+    if pda_intro.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let mut intro_data =
+        try_from_slice_unchecked::<StudentIntroAccountState>(&pda_intro.data.borrow()).unwrap();
+
+    if intro_data.discriminator != StudentIntroAccountState::DISCRIMINATOR {
+        msg!("Account is not an intro");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !intro_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(IntroError::UninitializedAccount.into());
+    }
+
+    if endorse {
+        intro_data.endorsed_by = Some(*instructor.key);
+        intro_data.endorsed_at = Some(Clock::get()?.unix_timestamp);
+    } else {
+        intro_data.endorsed_by = None;
+        intro_data.endorsed_at = None;
+    }
+    intro_data.serialize(&mut &mut pda_intro.data.borrow_mut()[..])?;
+
+    msg!(
+        "EVENT intro_endorsed intro={} student={} endorsed={} instructor={}",
+        pda_intro.key,
+        intro_data.student_key,
+        endorse,
+        instructor.key
+    );
+
+    Ok(())
+}
+
+fn load_cohort(program_id: &Pubkey, pda_cohort: &AccountInfo) -> Result<Cohort, ProgramError> {
+    if pda_cohort.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let cohort_data = try_from_slice_unchecked::<Cohort>(&pda_cohort.data.borrow())?;
+    if !cohort_data.is_initialized() {
+        msg!("Cohort is not initialized");
+        return Err(IntroError::UninitializedAccount.into());
+    }
+
+    let (pda, _bump_seed) = pda::find_cohort_address(program_id, cohort_data.cohort);
+    if pda != *pda_cohort.key {
+        msg!("Invalid seeds for PDA");
+        return Err(IntroError::InvalidPDA.into());
+    }
+
+    Ok(cohort_data)
+}
+
+// Checks that `instructor` signed and is the one named in the config
+fn load_instructor_config(
+    program_id: &Pubkey,
+    instructor: &AccountInfo,
+    pda_config: &AccountInfo,
+) -> Result<ProgramConfig, ProgramError> {
+    if !instructor.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if pda_config.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let (config, _config_bump) = pda::find_config_address(program_id);
+    if config != *pda_config.key {
+        msg!("Invalid seeds for PDA");
+        return Err(IntroError::InvalidPDA.into());
+    }
+
+    let config_data = try_from_slice_unchecked::<ProgramConfig>(&pda_config.data.borrow())?;
+    if !config_data.is_initialized() {
+        msg!("Config is not initialized");
+        return Err(IntroError::UninitializedAccount.into());
+    }
+
+    if config_data.instructor != *instructor.key {
+        msg!("Only the instructor can do this");
+        return Err(IntroError::InvalidInstructor.into());
+    }
+
+    Ok(config_data)
+}
+
 pub fn process_instruction(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
@@ -304,14 +854,28 @@ pub fn process_instruction(
 ) -> ProgramResult {
     let instruction = StudentIntroInstruction::unpack(instruction_data)?;
     match instruction {
-        StudentIntroInstruction::AddStudentIntro { name, msg } => {
-            add_student_intro(program_id, accounts, name, msg)
+        StudentIntroInstruction::AddStudentIntro { name, msg, cohort } => {
+            add_student_intro(program_id, accounts, name, msg, cohort)
         }
-        StudentIntroInstruction::UpdateStudentIntro { name, msg } => {
-            update_student_intro(program_id, accounts, name, msg)
+        StudentIntroInstruction::UpdateStudentIntro { name, msg, cohort } => {
+            update_student_intro(program_id, accounts, name, msg, cohort)
         }
         StudentIntroInstruction::ReplyToIntro { reply } => {
             reply_to_intro(program_id, accounts, reply)
         }
+        StudentIntroInstruction::SetInstructor { instructor } => {
+            set_instructor(program_id, accounts, instructor)
+        }
+        StudentIntroInstruction::CreateCohort { cohort } => {
+            create_cohort(program_id, accounts, cohort)
+        }
+        StudentIntroInstruction::SetCohortOpen { is_open } => {
+            set_cohort_open(program_id, accounts, is_open)
+        }
+        StudentIntroInstruction::DeleteStudentIntro => delete_student_intro(program_id, accounts),
+        StudentIntroInstruction::UpdateReply { reply } => update_reply(program_id, accounts, reply),
+        StudentIntroInstruction::DeleteReply => delete_reply(program_id, accounts),
+        StudentIntroInstruction::EndorseIntro => set_endorsement(program_id, accounts, true),
+        StudentIntroInstruction::RevokeEndorsement => set_endorsement(program_id, accounts, false),
     }
 }
@@ -11,6 +11,30 @@ pub struct StudentIntroAccountState {
     pub student_key: Pubkey,
     pub name: String,
     pub msg: String,
+    pub cohort: u64,
+    // Set by the instructor to mark a verified, enrolled student
+    pub endorsed_by: Option<Pubkey>,
+    pub endorsed_at: Option<i64>,
+    // Replies numbered below this were left on an earlier intro at the same address
+    pub first_reply: u64,
+}
+
+// Names the instructor who can create and open cohorts and endorse intros
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ProgramConfig {
+    pub discriminator: String,
+    pub is_initialized: bool,
+    pub instructor: Pubkey,
+}
+
+// Intros can only be added to a cohort while it's open
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct Cohort {
+    pub discriminator: String,
+    pub is_initialized: bool,
+    pub cohort: u64,
+    pub instructor: Pubkey,
+    pub is_open: bool,
 }
 
 #[derive(BorshSerialize, BorshDeserialize)]
@@ -33,7 +57,15 @@ pub struct IntroReply {
 impl StudentIntroAccountState {
     pub const DISCRIMINATOR: &'static str = "intro";
     pub fn get_account_size(name: String, msg: String) -> usize {
-        return (4 + Self::DISCRIMINATOR.len()) + 1 + 32 + (4 + name.len()) + (4 + msg.len());
+        return (4 + Self::DISCRIMINATOR.len())
+            + 1
+            + 32
+            + (4 + name.len())
+            + (4 + msg.len())
+            + 8
+            + (1 + 32)
+            + (1 + 8)
+            + 8;
     }
 }
 
@@ -49,6 +81,16 @@ impl IntroReplyCounter {
     pub const SIZE: usize = (4 + Self::DISCRIMINATOR.len()) + 1 + 8;
 }
 
+impl ProgramConfig {
+    pub const DISCRIMINATOR: &'static str = "config";
+    pub const SIZE: usize = (4 + Self::DISCRIMINATOR.len()) + 1 + 32;
+}
+
+impl Cohort {
+    pub const DISCRIMINATOR: &'static str = "cohort";
+    pub const SIZE: usize = (4 + Self::DISCRIMINATOR.len()) + 1 + 8 + 32 + 1;
+}
+
 impl Sealed for StudentIntroAccountState {}
 impl Sealed for IntroReplyCounter {}
 
@@ -69,3 +111,15 @@ impl IsInitialized for IntroReply {
         self.is_initialized
     }
 }
+
+impl IsInitialized for ProgramConfig {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for Cohort {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
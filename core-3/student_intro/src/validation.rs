@@ -0,0 +1,148 @@
+use crate::error::IntroError;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+pub const MAX_NAME_GRAPHEMES: usize = 15;
+pub const MAX_MSG_GRAPHEMES: usize = 50;
+
+// Trimmed, NFC-normalized name of at most `MAX_NAME_GRAPHEMES`
+pub fn normalize_name(name: &str) -> Result<String, IntroError> {
+    let name = normalize(name)?;
+    if name.is_empty() {
+        return Err(IntroError::EmptyName);
+    }
+    if grapheme_count(&name) > MAX_NAME_GRAPHEMES {
+        return Err(IntroError::InvalidNameLength);
+    }
+    Ok(name)
+}
+
+// Trimmed, NFC-normalized intro message of at most `MAX_MSG_GRAPHEMES`
+pub fn normalize_msg(msg: &str) -> Result<String, IntroError> {
+    let msg = normalize(msg)?;
+    if msg.is_empty() {
+        return Err(IntroError::EmptyIntro);
+    }
+    if grapheme_count(&msg) > MAX_MSG_GRAPHEMES {
+        return Err(IntroError::InvalidIntroLength);
+    }
+    Ok(msg)
+}
+
+// Lengths are counted in extended grapheme clusters, what a reader sees as one
+// character. A Devanagari syllable with its vowel sign or an emoji with a skin
+// tone counts once even though it's several chars
+fn grapheme_count(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+fn normalize(text: &str) -> Result<String, IntroError> {
+    let text: String = text.trim().nfc().collect();
+
+    for c in text.chars() {
+        if is_bidi_control(c) {
+            return Err(IntroError::BidiOverride);
+        }
+        if c.is_control() || is_invisible(c) {
+            return Err(IntroError::ControlCharacter);
+        }
+    }
+
+    Ok(text)
+}
+
+// Marks, embeddings, overrides and isolates that can reorder how text is displayed
+fn is_bidi_control(c: char) -> bool {
+    matches!(
+        c,
+        '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+// Zero width space, non-joiner and joiner, word joiner and byte order mark
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trims_and_composes() {
+        assert_eq!(normalize_name("  Ada  ").unwrap(), "Ada");
+        // e followed by a combining acute accent becomes the precomposed é
+        assert_eq!(normalize_name("Ame\u{301}lie").unwrap(), "Am\u{e9}lie");
+        assert_eq!(normalize_msg("\tHello there\n").unwrap(), "Hello there");
+    }
+
+    #[test]
+    fn rejects_empty_text() {
+        assert_eq!(normalize_name(""), Err(IntroError::EmptyName));
+        assert_eq!(normalize_name(" \t\n "), Err(IntroError::EmptyName));
+        assert_eq!(normalize_msg("   "), Err(IntroError::EmptyIntro));
+    }
+
+    #[test]
+    fn rejects_bidi_controls() {
+        for c in [
+            '\u{061C}', '\u{200E}', '\u{200F}', '\u{202E}', '\u{2066}', '\u{2069}',
+        ] {
+            let name = format!("Ada{}Lovelace", c);
+            assert_eq!(normalize_name(&name), Err(IntroError::BidiOverride));
+        }
+    }
+
+    #[test]
+    fn rejects_control_and_invisible_characters() {
+        for c in [
+            '\u{0000}', '\u{0007}', '\u{001B}', '\u{007F}', '\u{0085}', '\u{200B}', '\u{200C}',
+            '\u{200D}', '\u{2060}', '\u{FEFF}',
+        ] {
+            let msg = format!("Hello{}there", c);
+            assert_eq!(normalize_msg(&msg), Err(IntroError::ControlCharacter));
+        }
+    }
+
+    #[test]
+    fn name_length_is_in_graphemes() {
+        let name = "a".repeat(MAX_NAME_GRAPHEMES);
+        assert!(normalize_name(&name).is_ok());
+        let name = "a".repeat(MAX_NAME_GRAPHEMES + 1);
+        assert_eq!(normalize_name(&name), Err(IntroError::InvalidNameLength));
+
+        // Ka with the vowel sign i is one grapheme made of two chars
+        let devanagari = "\u{915}\u{93F}".repeat(MAX_NAME_GRAPHEMES);
+        assert_eq!(devanagari.chars().count(), 2 * MAX_NAME_GRAPHEMES);
+        assert!(normalize_name(&devanagari).is_ok());
+        let devanagari = "\u{915}\u{93F}".repeat(MAX_NAME_GRAPHEMES + 1);
+        assert_eq!(
+            normalize_name(&devanagari),
+            Err(IntroError::InvalidNameLength)
+        );
+
+        // Thumbs up with a skin tone modifier and a flag are one grapheme each
+        let emoji = "\u{1F44D}\u{1F3FD}\u{1F1EE}\u{1F1F3}".repeat(MAX_NAME_GRAPHEMES / 2);
+        assert!(normalize_name(&emoji).is_ok());
+    }
+
+    #[test]
+    fn msg_length_is_in_graphemes() {
+        let msg = "\u{1F44D}\u{1F3FD}".repeat(MAX_MSG_GRAPHEMES);
+        assert!(normalize_msg(&msg).is_ok());
+        let msg = "\u{1F44D}\u{1F3FD}".repeat(MAX_MSG_GRAPHEMES + 1);
+        assert_eq!(normalize_msg(&msg), Err(IntroError::InvalidIntroLength));
+    }
+
+    #[test]
+    fn length_is_checked_after_trimming_and_nfc() {
+        let name = format!("  {}  ", "e\u{301}".repeat(MAX_NAME_GRAPHEMES));
+        assert_eq!(
+            normalize_name(&name).unwrap(),
+            "\u{e9}".repeat(MAX_NAME_GRAPHEMES)
+        );
+    }
+}